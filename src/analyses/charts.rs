@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::analyses::zipf;
+use crate::utils::{graph, utils};
+use crate::corpus::{FrequencyTable, TfIdfTable};
+use crate::error::Result;
+use crate::output::{self, OutputPaths};
+use crate::settings::Settings;
+
+// at most this many points are drawn for each line of the Zipf plot
+const ZIPF_PLOT_POINTS: usize = 400;
+
+/// The "charts" section of settings.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChartSettings {
+    /// Number of words in the bar charts
+    pub top_n: usize,
+    /// Number of words (columns) of the TF-IDF heatmap
+    pub heatmap_words: usize,
+}
+
+impl Default for ChartSettings {
+    fn default() -> Self {
+        ChartSettings {
+            top_n: 20,
+            heatmap_words: 30,
+        }
+    }
+}
+
+/// Most frequent words of a frequency map, most frequent first (ties in alphabetical order)
+pub fn top_words(frequencies: &HashMap<String, usize>, n: usize) -> Vec<(String, f64)> {
+    let mut words: Vec<(&String, &usize)> = frequencies.iter().collect();
+    words.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    words.into_iter().take(n).map(|(word, count)| (word.clone(), *count as f64)).collect()
+}
+
+/// Log-log rank/frequency plot of each file and of the corpus, with the maximum likelihood Zipf fit of the corpus (dashed)
+pub fn zipf_chart(fits: &[zipf::ZipfFit]) -> String {
+    let mut series: Vec<graph::Series> = Vec::new();
+    for (i, fit) in fits.iter().enumerate() {
+        // keep roughly log-spaced ranks, the tail has thousands of points on top of each other
+        let step = (fit.ranks.len() as f64).powf(1.0 / ZIPF_PLOT_POINTS as f64);
+        let mut next_rank = 1.0;
+        let mut points: Vec<(f64, f64)> = Vec::new();
+        for (j, (_, frequency)) in fit.ranks.iter().enumerate() {
+            let rank = (j + 1) as f64;
+            if rank >= next_rank || j + 1 == fit.ranks.len() {
+                points.push((rank, *frequency as f64));
+                next_rank = (rank * step).max(rank + 1.0);
+            }
+        }
+        let is_corpus = i + 1 == fits.len();
+        series.push(graph::Series {
+            name: fit.scope.clone(),
+            color: if is_corpus { "#222222".to_string() } else { graph::PALETTE[i % graph::PALETTE.len()].to_string() },
+            points,
+            dashed: false,
+        });
+    }
+
+    if let Some(corpus) = fits.last().filter(|fit| fit.zipf_mle.is_finite()) {
+        // expected frequency of rank r: tokens * r^-s / Σ k^-s
+        let normalization: f64 = (1..=corpus.types).map(|k| (k as f64).powf(-corpus.zipf_mle)).sum();
+        let points: Vec<(f64, f64)> = [1, corpus.types]
+            .iter()
+            .map(|rank| (*rank as f64, corpus.tokens as f64 * (*rank as f64).powf(-corpus.zipf_mle) / normalization))
+            .collect();
+        series.push(graph::Series {
+            name: format!("Zipf fit (s = {:.2})", corpus.zipf_mle),
+            color: "#e15759".to_string(),
+            points,
+            dashed: true,
+        });
+    }
+
+    graph::log_log_plot("Rank / frequency (Zipf)", "Rank", "Frequency", &series)
+}
+
+/// Chapter by word heatmap of the words with the highest total TF-IDF
+pub fn tf_idf_heatmap(tfidf: &TfIdfTable, words: usize) -> String {
+    let mut totals: HashMap<&String, f64> = HashMap::new();
+    for chapter in tfidf.files.values().flatten() {
+        for (word, value) in chapter {
+            *totals.entry(word).or_insert(0.0) += value;
+        }
+    }
+    let mut columns: Vec<(&String, f64)> = totals.into_iter().collect();
+    columns.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then_with(|| a.0.cmp(b.0)));
+    let columns: Vec<String> = columns.into_iter().take(words).map(|(word, _)| word.clone()).collect();
+
+    let mut rows: Vec<String> = Vec::new();
+    let mut values: Vec<Vec<f64>> = Vec::new();
+    for file_name in tfidf.file_names() {
+        let chapters = &tfidf.files[&file_name];
+        for (i, chapter) in chapters.iter().enumerate() {
+            rows.push(if chapters.len() > 1 { format!("{} _ #{}", file_name, i + 1) } else { file_name.clone() });
+            values.push(columns.iter().map(|word| chapter.get(word).copied().unwrap_or(0.0)).collect());
+        }
+    }
+
+    graph::heatmap("TF-IDF (no stopwords)", &rows, &columns, &values, (0x1f, 0x4e, 0x79))
+}
+
+// main draw the top words of each file, the Zipf plot and the TF-IDF heatmap into the outputs folder
+// tfidf: TF-IDF without stopwords (empty if there is a single document), zipf_fits: from zipf::calculate_zipf
+pub fn main(paths: &OutputPaths, frequencies: &FrequencyTable, tfidf: &TfIdfTable, zipf_fits: &[zipf::ZipfFit], settings: &Settings) -> Result<()> {
+    println!("------------------------------------------------------------");
+    println!("DRAWING CHARTS...");
+    // start time for the whole function
+    let start_total = std::time::Instant::now();
+
+    let chart_settings = &settings.charts;
+    utils::create_output_folder(&paths.directory)?;
+
+    let start = std::time::Instant::now();
+    let without_stopwords = frequencies.without_stopwords();
+    let file_totals = without_stopwords.file_totals();
+    for file_name in without_stopwords.file_names() {
+        let bars = top_words(&file_totals[&file_name], chart_settings.top_n);
+        let svg = graph::bar_chart(&format!("Top {} words: {}", bars.len(), file_name), &bars, graph::PALETTE[0]);
+        let output_file_path = paths.file(&format!("chart_top-words_{}", output::file_name_part(&file_name)), "svg");
+        utils::write_output_file(&output_file_path, svg.as_bytes())?;
+    }
+    let bars = top_words(&without_stopwords.corpus_totals(), chart_settings.top_n);
+    let svg = graph::bar_chart(&format!("Top {} words: {}", bars.len(), paths.folder_name), &bars, graph::PALETTE[0]);
+    let output_file_path = paths.file("chart_top-words", "svg");
+    utils::write_output_file(&output_file_path, svg.as_bytes())?;
+    let duration = start.elapsed();
+    println!("\x1b[2m  Bar charts drawn in {} ms\x1b[0m", duration.as_millis());
+
+    let start = std::time::Instant::now();
+    let output_file_path = paths.file("chart_zipf", "svg");
+    utils::write_output_file(&output_file_path, zipf_chart(zipf_fits).as_bytes())?;
+    let duration = start.elapsed();
+    println!("\x1b[2m  Zipf plot drawn in {} ms\x1b[0m", duration.as_millis());
+
+    if !tfidf.is_empty() {
+        let start = std::time::Instant::now();
+        let output_file_path = paths.file("chart_tf-idf-heatmap", "svg");
+        utils::write_output_file(&output_file_path, tf_idf_heatmap(tfidf, chart_settings.heatmap_words).as_bytes())?;
+        let duration = start.elapsed();
+        println!("\x1b[2m  TF-IDF heatmap drawn in {} ms\x1b[0m", duration.as_millis());
+    }
+    else {
+        println!("Only a single document with no chapters, skipping the TF-IDF heatmap");
+    }
+
+    // end time
+    let duration = start_total.elapsed();
+    println!("Charts completed in {} ms", duration.as_millis());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn top_words_ties_in_alphabetical_order() {
+        let frequencies = HashMap::from([("pear".to_string(), 2), ("apple".to_string(), 2), ("fig".to_string(), 5), ("kiwi".to_string(), 1)]);
+        let top = top_words(&frequencies, 3);
+        assert_eq!(top, [("fig".to_string(), 5.0), ("apple".to_string(), 2.0), ("pear".to_string(), 2.0)]);
+    }
+
+    #[test]
+    fn heatmap_of_the_largest_totals() {
+        let chapter = |values: &[(&str, f64)]| values.iter().map(|(word, value)| (word.to_string(), *value)).collect::<HashMap<String, f64>>();
+        let tfidf = TfIdfTable {
+            files: HashMap::from([
+                ("a".to_string(), vec![chapter(&[("dragon", 0.5), ("ring", 0.1)]), chapter(&[("dragon", 0.4)])]),
+                ("b".to_string(), vec![chapter(&[("sword", 0.8), ("ring", 0.2)])]),
+            ]),
+        };
+        let svg = tf_idf_heatmap(&tfidf, 2);
+        // dragon 0.9, sword 0.8, ring 0.3
+        let (dragon, sword) = (svg.find(">dragon</text>").unwrap(), svg.find(">sword</text>").unwrap());
+        assert!(dragon < sword);
+        assert!(!svg.contains(">ring</text>"));
+        assert!(svg.contains(">a _ #2</text>"));
+    }
+}
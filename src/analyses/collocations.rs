@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{tables, utils};
+use crate::corpus::Corpus;
+use crate::error::Result;
+use crate::output::OutputPaths;
+use crate::settings::Settings;
+use crate::normalizer::TermForm;
+
+/// The "collocations" section of settings.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CollocationSettings {
+    /// How many words after a word are paired with it for the window-based table (1 = adjacent pairs only)
+    pub window: usize,
+    /// Pairs seen fewer times than this (in a file, or in the whole corpus) are left out
+    pub min_freq: usize,
+    /// Leave out the pairs containing a stopword
+    pub skip_stopwords: bool,
+}
+
+impl Default for CollocationSettings {
+    fn default() -> Self {
+        CollocationSettings {
+            window: 5,
+            min_freq: 3,
+            skip_stopwords: true,
+        }
+    }
+}
+
+/// Association scores of an ordered word pair ("first" is followed by "second")
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collocation {
+    pub first: String,
+    pub second: String,
+    /// Number of times the pair was seen
+    pub frequency: usize,
+    pub first_frequency: usize,
+    pub second_frequency: usize,
+    /// Pointwise mutual information, log2(observed / expected)
+    pub pmi: f64,
+    /// Dunning's log-likelihood (G²)
+    pub log_likelihood: f64,
+    pub t_score: f64,
+    pub dice: f64,
+}
+
+/// Ranked collocations of each file and of the whole corpus
+#[derive(Debug, Clone, Default)]
+pub struct CollocationTable {
+    pub files: HashMap<String, Vec<Collocation>>,
+    pub corpus: Vec<Collocation>,
+}
+
+// word and pair counts of a file or of the corpus
+// a "slot" is a position pair (word, next word within the window) inside a chapter: the sample space of the contingency tables
+#[derive(Default)]
+struct Counts {
+    tokens: usize,
+    slots: usize,
+    words: HashMap<String, usize>,
+    // number of slots with the word on the left, and on the right
+    left: HashMap<String, usize>,
+    right: HashMap<String, usize>,
+    pairs: HashMap<(String, String), usize>,
+}
+
+fn add_counts(target: &mut HashMap<String, usize>, other: &HashMap<String, usize>) {
+    for (word, count) in other {
+        *target.entry(word.clone()).or_insert(0) += count;
+    }
+}
+
+impl Counts {
+    fn merge(&mut self, other: &Counts) {
+        self.tokens += other.tokens;
+        self.slots += other.slots;
+        add_counts(&mut self.words, &other.words);
+        add_counts(&mut self.left, &other.left);
+        add_counts(&mut self.right, &other.right);
+        for (pair, count) in &other.pairs {
+            *self.pairs.entry(pair.clone()).or_insert(0) += count;
+        }
+    }
+}
+
+// pairs ("word" followed by "next" within the window) never cross chapter boundaries
+// every slot counts in the sample space, but only the pairs without stopwords (if skipped) are counted as observed
+fn count_pairs(chapters: &[&[String]], window: usize, skip_stopwords: bool) -> Counts {
+    let mut counts = Counts::default();
+    for words in chapters {
+        counts.tokens += words.len();
+        for (i, word) in words.iter().enumerate() {
+            *counts.words.entry(word.clone()).or_insert(0) += 1;
+            let after = window.min(words.len() - 1 - i);
+            let before = window.min(i);
+            counts.slots += after;
+            *counts.left.entry(word.clone()).or_insert(0) += after;
+            *counts.right.entry(word.clone()).or_insert(0) += before;
+            if skip_stopwords && utils::is_stopword_any_case(word) {
+                continue;
+            }
+            for next in words.iter().skip(i + 1).take(window) {
+                if skip_stopwords && utils::is_stopword_any_case(next) {
+                    continue;
+                }
+                *counts.pairs.entry((word.clone(), next.clone())).or_insert(0) += 1;
+            }
+        }
+    }
+    counts
+}
+
+// o * ln(o / e), 0 when o is 0
+fn ll_term(observed: f64, expected: f64) -> f64 {
+    if observed > 0.0 && expected > 0.0 {
+        observed * (observed / expected).ln()
+    } else {
+        0.0
+    }
+}
+
+// Contingency table of a pair over the N window slots of the text:
+// O11 = pair frequency, R1 = slots with "first" on the left, C1 = slots with "second" on the right, E11 = R1 * C1 / N
+// a window of 1 gives the usual adjacent-pair (bigram) statistics
+fn score(first: &str, second: &str, frequency: usize, counts: &Counts) -> Collocation {
+    let first_frequency = counts.words.get(first).copied().unwrap_or(0);
+    let second_frequency = counts.words.get(second).copied().unwrap_or(0);
+    let n = counts.slots as f64;
+    let o11 = frequency as f64;
+    let r1 = counts.left.get(first).copied().unwrap_or(0) as f64;
+    let c1 = counts.right.get(second).copied().unwrap_or(0) as f64;
+
+    let (o12, o21, o22) = (r1 - o11, c1 - o11, n - r1 - c1 + o11);
+    let (r2, c2) = (n - r1, n - c1);
+    let e11 = r1 * c1 / n;
+    let log_likelihood = 2.0 * (ll_term(o11, e11) + ll_term(o12, r1 * c2 / n) + ll_term(o21, r2 * c1 / n) + ll_term(o22, r2 * c2 / n));
+
+    Collocation {
+        first: first.to_string(),
+        second: second.to_string(),
+        frequency,
+        first_frequency,
+        second_frequency,
+        pmi: (o11 / e11).log2(),
+        log_likelihood,
+        t_score: (o11 - e11) / o11.sqrt(),
+        dice: 2.0 * o11 / (r1 + c1),
+    }
+}
+
+// score every pair seen at least min_freq times, strongest (log-likelihood) first
+fn rank(counts: &Counts, min_freq: usize) -> Vec<Collocation> {
+    let mut result: Vec<Collocation> = counts.pairs
+        .iter()
+        .filter(|(_, frequency)| **frequency >= min_freq.max(1))
+        .map(|((first, second), frequency)| score(first, second, *frequency, counts))
+        .collect();
+    result.sort_by(|a, b| {
+        b.log_likelihood.partial_cmp(&a.log_likelihood).unwrap()
+            .then_with(|| b.frequency.cmp(&a.frequency))
+            .then_with(|| (&a.first, &a.second).cmp(&(&b.first, &b.second)))
+    });
+    result
+}
+
+/// Score the pairs of words at most `window` words apart (1 = adjacent pairs), in each file and in the whole corpus
+pub fn calculate_collocations(corpus: &Corpus, form: TermForm, window: usize, min_freq: usize, skip_stopwords: bool) -> CollocationTable {
+    let start = std::time::Instant::now();
+    let window = window.max(1);
+
+    let file_counts: Vec<(String, Counts)> = corpus.documents
+        .par_iter()
+        .map(|document| {
+            let chapters: Vec<&[String]> = document.chapters.iter().map(|chapter| chapter.terms(form)).collect();
+            (document.name.clone(), count_pairs(&chapters, window, skip_stopwords))
+        })
+        .collect();
+
+    let mut corpus_counts = Counts::default();
+    for (_, counts) in &file_counts {
+        corpus_counts.merge(counts);
+    }
+
+    let files: HashMap<String, Vec<Collocation>> = file_counts
+        .par_iter()
+        .map(|(file_name, counts)| (file_name.clone(), rank(counts, min_freq)))
+        .collect();
+    let corpus = rank(&corpus_counts, min_freq);
+
+    let duration = start.elapsed();
+    println!("\x1b[2m  Scored {} word pairs (window of {}) in {} ms\x1b[0m", corpus_counts.pairs.len(), window, duration.as_millis());
+
+    CollocationTable { files, corpus }
+}
+
+// main score the adjacent pairs, and the window-based pairs if the window is larger than 1, and write one ranked table for each
+pub fn main(paths: &OutputPaths, corpus: &Corpus, settings: &Settings) -> Result<()> {
+    println!("------------------------------------------------------------");
+    println!("ANALYZING COLLOCATIONS...");
+    // start time for the whole function
+    let start_total = std::time::Instant::now();
+
+    let collocation_settings = &settings.collocations;
+    utils::create_output_folder(&paths.directory)?;
+
+    let mut windows: Vec<usize> = vec![1];
+    if collocation_settings.window > 1 {
+        windows.push(collocation_settings.window);
+    }
+    for window in windows {
+        let collocations = calculate_collocations(corpus, settings.normalization.report, window, collocation_settings.min_freq, collocation_settings.skip_stopwords);
+        let csv_string = tables::collocations_to_csv_string(&collocations, &settings.output)?;
+        let table_name = if window == 1 { "adjacent".to_string() } else { format!("window-{}", window) };
+        let output_file_path = paths.file(&format!("collocations_{}", table_name), "csv");
+        utils::write_output_file(&output_file_path, csv_string.as_bytes())?;
+    }
+
+    // end time
+    let duration = start_total.elapsed();
+    println!("Collocation analysis completed in {} ms", duration.as_millis());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyses::test_util::{assert_close, words};
+
+    fn pair(counts: &Counts, first: &str, second: &str) -> Collocation {
+        let collocations = rank(counts, 1);
+        collocations.into_iter().find(|c| c.first == first && c.second == second).unwrap()
+    }
+
+    #[test]
+    fn adjacent_pairs() {
+        // 4 slots: (x y) (y x) (x y) (y z), O11 = 2, R1 = 2, C1 = 2, E11 = 1
+        let chapter = words("x y x y z");
+        let counts = count_pairs(&[&chapter], 1, false);
+        assert_eq!((counts.tokens, counts.slots), (5, 4));
+
+        let xy = pair(&counts, "x", "y");
+        assert_eq!((xy.frequency, xy.first_frequency, xy.second_frequency), (2, 2, 2));
+        assert_close(xy.pmi, 1.0, 1e-6);
+        assert_close(xy.t_score, 1.0 / 2f64.sqrt(), 1e-6);
+        assert_close(xy.dice, 1.0, 1e-6);
+        // O12 = O21 = 0, O22 = 2 with E22 = 1: 2 * (2 ln 2 + 2 ln 2)
+        assert_close(xy.log_likelihood, 8.0 * 2f64.ln(), 1e-6);
+    }
+
+    #[test]
+    fn window_pairs() {
+        // window of 5: 4 + 3 + 2 + 1 = 10 slots, R1 = 4 + 2 = 6 (after each x), C1 = 1 + 3 = 4 (before each y)
+        let chapter = words("x y x y z");
+        let counts = count_pairs(&[&chapter], 5, false);
+        assert_eq!(counts.slots, 10);
+
+        // O11 = 3, E11 = 2.4, and O12 = 3, O21 = 1, O22 = 3 with E12 = 3.6, E21 = 1.6, E22 = 2.4
+        let xy = pair(&counts, "x", "y");
+        assert_eq!(xy.frequency, 3);
+        assert_close(xy.pmi, 1.25f64.log2(), 1e-6);
+        assert_close(xy.t_score, 0.6 / 3f64.sqrt(), 1e-6);
+        assert_close(xy.dice, 0.6, 1e-6);
+        let expected = 2.0 * (3.0 * (3.0f64 / 2.4).ln() + 3.0 * (3.0f64 / 3.6).ln() + (1.0f64 / 1.6).ln() + 3.0 * (3.0f64 / 2.4).ln());
+        assert_close(xy.log_likelihood, expected, 1e-6);
+        assert_close(xy.log_likelihood, 0.643786, 1e-6);
+    }
+
+    #[test]
+    fn chapters_and_stopwords() {
+        // no pair across the two chapters, and "the" is never paired
+        let (first, second) = (words("gold the ring"), words("heavy rain"));
+        let counts = count_pairs(&[&first, &second], 1, true);
+        assert_eq!(counts.slots, 3);
+        let pairs: Vec<&(String, String)> = counts.pairs.keys().collect();
+        assert_eq!(pairs, [&("heavy".to_string(), "rain".to_string())]);
+    }
+}
@@ -0,0 +1,228 @@
+use std::path::PathBuf;
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{tables, utils};
+use crate::corpus::Corpus;
+use crate::error::{Error, Result};
+use crate::output::OutputPaths;
+use crate::settings::Settings;
+
+/// Order of the concordance lines of each query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConcordanceSort {
+    /// By file, chapter and offset
+    Position,
+    /// By the words just before the match, nearest first
+    Left,
+    /// By the words just after the match
+    Right,
+}
+
+/// The "concordance" section of settings.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConcordanceSettings {
+    /// Words or phrases to search for. The target phrases are used if this is empty
+    pub queries: Vec<String>,
+    /// Number of characters of context on each side of a match
+    pub context_width: usize,
+    pub sort: ConcordanceSort,
+    pub case_sensitive: bool,
+    /// Only match whole words, so that "cat" does not match "concatenate"
+    pub whole_words: bool,
+}
+
+impl Default for ConcordanceSettings {
+    fn default() -> Self {
+        ConcordanceSettings {
+            queries: Vec::new(),
+            context_width: 50,
+            sort: ConcordanceSort::Position,
+            case_sensitive: false,
+            whole_words: true,
+        }
+    }
+}
+
+/// One hit of a query in the raw text of a chapter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConcordanceLine {
+    pub query: String,
+    pub file: String,
+    /// Chapter number, starting at 1
+    pub chapter: usize,
+    /// Offset of the match in the chapter text, in characters
+    pub offset: usize,
+    pub left: String,
+    /// The matched text, as written in the file
+    pub matched: String,
+    pub right: String,
+}
+
+fn build_query(query: &str, settings: &ConcordanceSettings) -> std::result::Result<Regex, regex::Error> {
+    let pattern = regex::escape(query);
+    // \b only makes sense next to a word character, e.g. a query ending with "!" can still match
+    let starts_with_word = query.chars().next().is_some_and(char::is_alphanumeric);
+    let ends_with_word = query.chars().last().is_some_and(char::is_alphanumeric);
+    let pattern = match settings.whole_words {
+        true => format!("{}{}{}", if starts_with_word { r"\b" } else { "" }, pattern, if ends_with_word { r"\b" } else { "" }),
+        false => pattern,
+    };
+    RegexBuilder::new(&pattern).case_insensitive(!settings.case_sensitive).build()
+}
+
+// context is shown on a single line
+fn flatten(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+fn search_chapter(query: &str, regex: &Regex, file: &str, chapter: usize, text: &str, context_width: usize) -> Vec<ConcordanceLine> {
+    let mut result: Vec<ConcordanceLine> = Vec::new();
+    // character offset, counted incrementally from the previous match
+    let (mut last_byte, mut last_char) = (0, 0);
+    for found in regex.find_iter(text) {
+        let offset = last_char + text[last_byte..found.start()].chars().count();
+        (last_byte, last_char) = (found.start(), offset);
+
+        let before = &text[..found.start()];
+        let left_start = before.char_indices().rev().nth(context_width.saturating_sub(1)).map(|(i, _)| i).unwrap_or(0);
+        let left = if context_width == 0 { "" } else { &before[left_start..] };
+        let right: String = text[found.end()..].chars().take(context_width).collect();
+
+        result.push(ConcordanceLine {
+            query: query.to_string(),
+            file: file.to_string(),
+            chapter: chapter + 1,
+            offset,
+            left: flatten(left),
+            matched: found.as_str().to_string(),
+            right: flatten(&right),
+        });
+    }
+    result
+}
+
+/// Find every hit of the queries in the raw text of each chapter, grouped by query (in the given order) and sorted as set in the settings
+pub fn search(corpus: &Corpus, queries: &[String], settings: &ConcordanceSettings) -> Result<Vec<ConcordanceLine>> {
+    let start = std::time::Instant::now();
+    let mut result: Vec<ConcordanceLine> = Vec::new();
+
+    for query in queries.iter().filter(|query| !query.is_empty()) {
+        let regex = build_query(query, settings)
+            .map_err(|error| Error::Settings { path: PathBuf::new(), reason: format!("invalid concordance query \"{}\": {}", query, error) })?;
+        let mut lines: Vec<ConcordanceLine> = corpus.documents
+            .par_iter()
+            .flat_map(|document| {
+                document.chapters
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, chapter)| search_chapter(query, &regex, &document.name, i, &chapter.text, settings.context_width))
+                    .collect::<Vec<ConcordanceLine>>()
+            })
+            .collect();
+
+        lines.sort_by(|a, b| (&a.file, a.chapter, a.offset).cmp(&(&b.file, b.chapter, b.offset)));
+        match settings.sort {
+            ConcordanceSort::Position => {}
+            ConcordanceSort::Left => lines.sort_by_cached_key(|line| {
+                line.left.to_lowercase().split_whitespace().rev().map(|word| word.to_string()).collect::<Vec<String>>()
+            }),
+            ConcordanceSort::Right => lines.sort_by_cached_key(|line| line.right.to_lowercase()),
+        }
+        result.extend(lines);
+    }
+
+    let duration = start.elapsed();
+    println!("\x1b[2m  Found {} matches of {} queries in {} ms\x1b[0m", result.len(), queries.len(), duration.as_millis());
+
+    Ok(result)
+}
+
+// main search the raw text for the queries (or the target phrases) and write the concordance lines
+pub fn main(paths: &OutputPaths, corpus: &Corpus, settings: &Settings) -> Result<()> {
+    println!("------------------------------------------------------------");
+    println!("GENERATING CONCORDANCE...");
+    // start time for the whole function
+    let start_total = std::time::Instant::now();
+
+    let concordance_settings = &settings.concordance;
+    let queries = match concordance_settings.queries.is_empty() {
+        true => &settings.phrases,
+        false => &concordance_settings.queries,
+    };
+    if queries.is_empty() {
+        println!("\x1b[33m  WARNING: No concordance queries or target phrases, skipping the concordance\x1b[0m");
+        return Ok(());
+    }
+
+    let lines = search(corpus, queries, concordance_settings)?;
+
+    utils::create_output_folder(&paths.directory)?;
+    let csv_string = tables::concordance_to_csv_string(&lines, &settings.output)?;
+    let output_file_path = paths.file("concordance", "csv");
+    utils::write_output_file(&output_file_path, csv_string.as_bytes())?;
+
+    // end time
+    let duration = start_total.elapsed();
+    println!("Concordance completed in {} ms", duration.as_millis());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyses::test_util::{corpus, document};
+
+    fn settings(context_width: usize, sort: ConcordanceSort) -> ConcordanceSettings {
+        ConcordanceSettings { context_width, sort, ..ConcordanceSettings::default() }
+    }
+
+    fn search_one(corpus: &Corpus, query: &str, settings: &ConcordanceSettings) -> Vec<ConcordanceLine> {
+        search(corpus, &[query.to_string()], settings).unwrap()
+    }
+
+    #[test]
+    fn whole_words_and_case() {
+        let corpus = corpus(vec![document("a.txt", &["The cat sat. A Cat, not a concatenation."])]);
+        let lines = search_one(&corpus, "cat", &settings(50, ConcordanceSort::Position));
+        let matched: Vec<&str> = lines.iter().map(|line| line.matched.as_str()).collect();
+        assert_eq!(matched, ["cat", "Cat"]);
+
+        let case_sensitive = ConcordanceSettings { case_sensitive: true, ..settings(50, ConcordanceSort::Position) };
+        assert_eq!(search_one(&corpus, "cat", &case_sensitive).len(), 1);
+        let anywhere = ConcordanceSettings { whole_words: false, ..settings(50, ConcordanceSort::Position) };
+        assert_eq!(search_one(&corpus, "cat", &anywhere).len(), 3);
+    }
+
+    #[test]
+    fn context_and_offsets_in_characters() {
+        // "é" is 2 bytes: offsets and context widths are counted in characters
+        let corpus = corpus(vec![document("a.txt", &["été\nring one", "ring"])]);
+        let lines = search_one(&corpus, "ring", &settings(5, ConcordanceSort::Position));
+        assert_eq!(lines[0], ConcordanceLine {
+            query: "ring".to_string(),
+            file: "a.txt".to_string(),
+            chapter: 1,
+            offset: 4,
+            left: "été".to_string(),
+            matched: "ring".to_string(),
+            right: "one".to_string(),
+        });
+        assert_eq!((lines[1].chapter, lines[1].offset, lines[1].left.as_str()), (2, 0, ""));
+    }
+
+    #[test]
+    fn punctuation_queries_and_sorting() {
+        let corpus = corpus(vec![document("a.txt", &["zebra ring b. apple ring a. mango ring c. stop!"])]);
+        assert_eq!(search_one(&corpus, "stop!", &settings(10, ConcordanceSort::Position)).len(), 1);
+
+        let left: Vec<String> = search_one(&corpus, "ring", &settings(6, ConcordanceSort::Left)).into_iter().map(|line| line.left).collect();
+        assert_eq!(left, ["apple", "mango", "zebra"]);
+        let right: Vec<String> = search_one(&corpus, "ring", &settings(2, ConcordanceSort::Right)).into_iter().map(|line| line.right).collect();
+        assert_eq!(right, ["a", "b", "c"]);
+    }
+}
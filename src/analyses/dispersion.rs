@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use rayon::prelude::*;
+
+use crate::utils::{tables, utils};
+use crate::corpus::{Corpus, FrequencyTable};
+use crate::error::Result;
+use crate::output::{OutputPaths, OutputSettings};
+
+/// How evenly a word is spread over the chapters (the "parts") of the corpus
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dispersion {
+    pub word: String,
+    pub total: usize,
+    /// Number of chapters containing the word
+    pub range: usize,
+    /// Juilland's D, from 0 (all in one chapter) to 1 (perfectly even)
+    pub juilland_d: f64,
+    /// Gries' deviation of proportions, from 0 (perfectly even) to nearly 1 (all in one chapter)
+    pub dp: f64,
+    /// Coefficient of variation of the relative frequencies in the chapters
+    pub cv: f64,
+}
+
+/// Number of words in each chapter of each document: <file name, chapters<words>>
+pub fn chapter_sizes(corpus: &Corpus) -> HashMap<String, Vec<usize>> {
+    corpus.documents
+        .iter()
+        .map(|document| (document.name.clone(), document.chapters.iter().map(|chapter| chapter.tokens).collect()))
+        .collect()
+}
+
+// v: frequency of the word in each part, sizes: number of words in each part
+fn dispersion(word: &str, v: &[usize], sizes: &[usize]) -> Dispersion {
+    let n = v.len() as f64;
+    let total: usize = v.iter().sum();
+    let corpus_size: usize = sizes.iter().sum();
+
+    // relative frequencies, so that long chapters do not look like peaks
+    let relative: Vec<f64> = v.iter().zip(sizes).map(|(count, size)| if *size == 0 { 0.0 } else { *count as f64 / *size as f64 }).collect();
+    let mean = relative.iter().sum::<f64>() / n;
+    let sd = (relative.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / n).sqrt();
+    let cv = if mean > 0.0 { sd / mean } else { 0.0 };
+
+    // DP: half the sum of the differences between the share of the word and the share of the corpus in each part
+    let dp = 0.5 * v.iter().zip(sizes)
+        .map(|(count, size)| (*count as f64 / total.max(1) as f64 - *size as f64 / corpus_size.max(1) as f64).abs())
+        .sum::<f64>();
+
+    Dispersion {
+        word: word.to_string(),
+        total,
+        range: v.iter().filter(|count| **count > 0).count(),
+        // cv is at most sqrt(n - 1), clamped against rounding errors
+        juilland_d: (1.0 - cv / (n - 1.0).sqrt()).max(0.0),
+        dp,
+        cv,
+    }
+}
+
+/// Dispersion of every word of the table over all the chapters of all the files, most frequent first
+/// `sizes` is the number of words in each chapter, see [`chapter_sizes`]
+pub fn calculate_dispersion(frequencies: &FrequencyTable, sizes: &HashMap<String, Vec<usize>>) -> Vec<Dispersion> {
+    let start = std::time::Instant::now();
+    let file_names = frequencies.file_names();
+    // the parts, in a fixed order: (file name, chapter index)
+    let parts: Vec<(&String, usize)> = file_names
+        .iter()
+        .flat_map(|file_name| (0..frequencies.files[file_name].len()).map(move |i| (file_name, i)))
+        .collect();
+    let part_sizes: Vec<usize> = parts
+        .iter()
+        .map(|(file_name, i)| sizes.get(*file_name).and_then(|chapters| chapters.get(*i)).copied().unwrap_or(0))
+        .collect();
+
+    // target phrases that were never found have a count of 0, and no dispersion
+    let mut words: Vec<(String, usize)> = frequencies.corpus_totals().into_iter().filter(|(_, total)| *total > 0).collect();
+    words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let result: Vec<Dispersion> = words
+        .par_iter()
+        .map(|(word, _)| {
+            let v: Vec<usize> = parts
+                .iter()
+                .map(|(file_name, i)| frequencies.files[*file_name][*i].get(word).copied().unwrap_or(0))
+                .collect();
+            dispersion(word, &v, &part_sizes)
+        })
+        .collect();
+
+    let duration = start.elapsed();
+    println!("\x1b[2m  Dispersion of {} words over {} chapters calculated in {} ms\x1b[0m", result.len(), parts.len(), duration.as_millis());
+
+    result
+}
+
+// main calculate the dispersion of every word over the chapters and write it as a companion of the word frequency tables
+pub fn main(paths: &OutputPaths, corpus: &Corpus, frequencies: &FrequencyTable, output: &OutputSettings) -> Result<()> {
+    // like TF-IDF, dispersion needs more than one document unit
+    if frequencies.document_count() <= 1 {
+        println!("------------------------------------------------------------");
+        println!("Only a single document with no chapters, skipping dispersion analysis");
+        return Ok(());
+    }
+
+    println!("------------------------------------------------------------");
+    println!("ANALYZING DISPERSION...");
+    // start time for the whole function
+    let start_total = std::time::Instant::now();
+
+    let sizes = chapter_sizes(corpus);
+    utils::create_output_folder(&paths.directory)?;
+
+    let dispersions = calculate_dispersion(frequencies, &sizes);
+    let csv_string = tables::dispersion_to_csv_string(&dispersions, output)?;
+    let output_file_path = paths.file("dispersion", "csv");
+    utils::write_output_file(&output_file_path, csv_string.as_bytes())?;
+
+    let dispersions = calculate_dispersion(&frequencies.without_stopwords(), &sizes);
+    let csv_string = tables::dispersion_to_csv_string(&dispersions, output)?;
+    let output_file_path = paths.file("dispersion_no-stopwords", "csv");
+    utils::write_output_file(&output_file_path, csv_string.as_bytes())?;
+
+    // end time
+    let duration = start_total.elapsed();
+    println!("Dispersion analysis completed in {} ms", duration.as_millis());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyses::test_util::assert_close;
+
+    #[test]
+    fn gries_example() {
+        // Gries (2008): 5 parts of 9, 10, 10, 10 and 11 words, a word seen 1, 2, 3, 4 and 5 times, DP = 0.18
+        let result = dispersion("a", &[1, 2, 3, 4, 5], &[9, 10, 10, 10, 11]);
+        assert_eq!((result.total, result.range), (15, 5));
+        assert_close(result.dp, 0.18, 1e-6);
+        // relative frequencies 1/9, 0.2, 0.3, 0.4, 5/11: mean 0.293131, population sd 0.125958
+        assert_close(result.cv, 0.429699, 1e-6);
+        assert_close(result.juilland_d, 1.0 - 0.429699 / 2.0, 1e-6);
+    }
+
+    #[test]
+    fn even_and_clumped() {
+        let even = dispersion("even", &[5, 5, 5], &[100, 100, 100]);
+        assert_close(even.juilland_d, 1.0, 1e-6);
+        assert_close(even.dp, 0.0, 1e-6);
+
+        // all in one of 3 equal parts: D = 0, DP = (1 - 1/3 + 1/3 + 1/3) / 2
+        let clumped = dispersion("clumped", &[0, 0, 9], &[100, 100, 100]);
+        assert_close(clumped.juilland_d, 0.0, 1e-6);
+        assert_close(clumped.dp, 2.0 / 3.0, 1e-6);
+        assert_eq!(clumped.range, 1);
+    }
+
+    #[test]
+    fn unseen_phrases_are_left_out() {
+        let chapters = vec![HashMap::from([("ring".to_string(), 2), ("gold ring".to_string(), 0)]), HashMap::from([("ring".to_string(), 1)])];
+        let frequencies = FrequencyTable { files: HashMap::from([("a.txt".to_string(), chapters)]) };
+        let sizes = HashMap::from([("a.txt".to_string(), vec![10, 10])]);
+        let words: Vec<String> = calculate_dispersion(&frequencies, &sizes).into_iter().map(|dispersion| dispersion.word).collect();
+        assert_eq!(words, ["ring"]);
+    }
+}
@@ -0,0 +1,73 @@
+use serde_json::Value;
+
+use crate::utils::{columnar, json, sqlite, utils};
+use crate::corpus::{Corpus, FrequencyTable, TfIdfTable};
+use crate::error::{Error, Result};
+use crate::output::{OutputFormat, OutputPaths};
+use crate::settings::Settings;
+
+// main write the word frequency, phrase and TF-IDF results in the machine-readable formats listed in the output settings
+// tfidf is the table from tf_idf::main (empty if TF-IDF was not calculated)
+pub fn main(paths: &OutputPaths, corpus: &Corpus, frequencies: &FrequencyTable, tfidf: &TfIdfTable, settings: &Settings, run: &json::RunInfo) -> Result<()> {
+    let output = &settings.output;
+    if ![OutputFormat::Json, OutputFormat::Ndjson, OutputFormat::Sqlite, OutputFormat::Parquet].iter().any(|format| output.writes(*format)) {
+        return Ok(());
+    }
+
+    println!("------------------------------------------------------------");
+    println!("EXPORTING RESULTS...");
+    // start time for the whole function
+    let start_total = std::time::Instant::now();
+
+    utils::create_output_folder(&paths.directory)?;
+    let metadata = json::metadata(corpus, frequencies, settings, run);
+
+    if output.writes(OutputFormat::Json) {
+        let json_string = json::results_to_json_string(metadata.clone(), corpus, frequencies, tfidf, &settings.phrases);
+        let output_file_path = paths.file("results", "json");
+        utils::write_output_file(&output_file_path, json_string.as_bytes())?;
+    }
+    if output.writes(OutputFormat::Ndjson) {
+        let ndjson_string = json::results_to_ndjson_string(metadata.clone(), corpus, frequencies, tfidf, &settings.phrases);
+        let output_file_path = paths.file("results", "ndjson");
+        utils::write_output_file(&output_file_path, ndjson_string.as_bytes())?;
+    }
+    if output.writes(OutputFormat::Sqlite) {
+        // the metadata table has the top-level fields of the JSON metadata: strings as they are, the rest (files, settings) as JSON
+        let pairs: Vec<(String, String)> = match metadata {
+            Value::Object(fields) => fields
+                .into_iter()
+                .map(|(key, value)| match value {
+                    Value::String(text) => (key, text),
+                    other => (key, other.to_string()),
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let output_file_path = paths.file("results", "sqlite");
+        sqlite::write_database(&output_file_path, corpus, frequencies, tfidf, &settings.phrases, &pairs)?;
+    }
+    if output.writes(OutputFormat::Parquet) {
+        let mut tables = vec![("frequency", columnar::frequency_columns(frequencies, &settings.phrases))];
+        if !settings.phrases.is_empty() {
+            tables.push(("phrases", columnar::phrase_columns(frequencies, &settings.phrases)));
+        }
+        if !tfidf.is_empty() {
+            tables.push(("tf-idf", columnar::tf_idf_columns(tfidf)));
+        }
+        for (name, columns) in tables {
+            let start = std::time::Instant::now();
+            let output_file_path = paths.file(name, "parquet");
+            columnar::write_table(&output_file_path, &columns)
+                .map_err(|error| Error::Output { path: output_file_path.clone(), source: std::io::Error::other(error) })?;
+            let duration = start.elapsed();
+            println!("\x1b[2m  {} written in {} ms\x1b[0m", output_file_path.display(), duration.as_millis());
+        }
+    }
+
+    // end time
+    let duration = start_total.elapsed();
+    println!("Export completed in {} ms", duration.as_millis());
+
+    Ok(())
+}
@@ -0,0 +1,270 @@
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::fs;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{files, tables, utils};
+use crate::corpus::{Corpus, FrequencyTable};
+use crate::error::{Error, Result};
+use crate::output::OutputPaths;
+use crate::settings::Settings;
+use crate::tokenizer;
+
+/// The "keyness" section of settings.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeynessSettings {
+    /// Folder of reference files (digested with the same settings), or a saved frequency table (e.g. a `_wordFreq.csv` output)
+    pub reference: String,
+    /// Significance level of the log-likelihood: 0.05, 0.01, 0.001 or 0.0001
+    pub p_value: f64,
+    /// Words seen fewer times than this in the target and reference together are left out
+    pub min_frequency: usize,
+}
+
+impl Default for KeynessSettings {
+    fn default() -> Self {
+        KeynessSettings {
+            reference: String::new(),
+            p_value: 0.05,
+            min_frequency: 5,
+        }
+    }
+}
+
+// (p-value, critical value of the chi-squared distribution with 1 degree of freedom)
+const CRITICAL_VALUES: [(f64, f64); 4] = [(0.05, 3.84), (0.01, 6.63), (0.001, 10.83), (0.0001, 15.13)];
+
+/// Critical value of the log-likelihood for a significance level
+pub fn critical_value(p_value: f64) -> std::result::Result<f64, String> {
+    CRITICAL_VALUES
+        .iter()
+        .find(|(p, _)| *p == p_value)
+        .map(|(_, critical)| *critical)
+        .ok_or_else(|| format!("unsupported keyness p_value {} (use 0.05, 0.01, 0.001 or 0.0001)", p_value))
+}
+
+/// Keyness scores of a word in the target corpus compared with the reference corpus
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyword {
+    pub word: String,
+    pub target_frequency: usize,
+    pub reference_frequency: usize,
+    /// Frequency per million words
+    pub target_per_million: f64,
+    pub reference_per_million: f64,
+    /// Log-likelihood (G²)
+    pub log_likelihood: f64,
+    pub chi_squared: f64,
+    /// Difference of the normalized frequencies, in % of the reference one
+    pub percent_diff: f64,
+    /// Hardie's log ratio, log2 of the ratio of the normalized frequencies
+    pub log_ratio: f64,
+}
+
+impl Keyword {
+    /// Whether the word is more frequent in the target than in the reference
+    pub fn is_positive(&self) -> bool {
+        self.target_per_million > self.reference_per_million
+    }
+}
+
+// o * ln(o / e), 0 when o is 0
+fn ll_term(observed: f64, expected: f64) -> f64 {
+    if observed > 0.0 {
+        observed * (observed / expected).ln()
+    } else {
+        0.0
+    }
+}
+
+// a, b: frequency of the word in the target and reference, c, d: total number of words in the target and reference
+fn score(word: &str, a: usize, b: usize, c: f64, d: f64) -> Keyword {
+    let (a_f, b_f) = (a as f64, b as f64);
+    let n = c + d;
+    let expected_target = c * (a_f + b_f) / n;
+    let expected_reference = d * (a_f + b_f) / n;
+    let log_likelihood = 2.0 * (ll_term(a_f, expected_target) + ll_term(b_f, expected_reference));
+
+    // 2x2 contingency table: word / other words in target / reference
+    let (other_target, other_reference) = (c - a_f, d - b_f);
+    let denominator = (a_f + b_f) * (other_target + other_reference) * c * d;
+    let chi_squared = if denominator > 0.0 {
+        n * (a_f * other_reference - b_f * other_target).powi(2) / denominator
+    } else {
+        0.0
+    };
+
+    // a frequency of 0 is replaced by 0.5 for the effect sizes, so that they stay finite
+    let target_relative = a_f.max(0.5) / c;
+    let reference_relative = b_f.max(0.5) / d;
+
+    Keyword {
+        word: word.to_string(),
+        target_frequency: a,
+        reference_frequency: b,
+        target_per_million: a_f / c * 1_000_000.0,
+        reference_per_million: b_f / d * 1_000_000.0,
+        log_likelihood,
+        chi_squared,
+        percent_diff: (target_relative - reference_relative) * 100.0 / reference_relative,
+        log_ratio: (target_relative / reference_relative).log2(),
+    }
+}
+
+/// Word totals of a frequency table, without the target phrases (they are counted on top of the words, not as words)
+pub fn word_totals(frequencies: &FrequencyTable, phrases: &[String]) -> HashMap<String, usize> {
+    let mut totals = frequencies.corpus_totals();
+    totals.retain(|word, _| !phrases.contains(word));
+    totals
+}
+
+/// Compare the word totals of the target with those of the reference, strongest (log-likelihood) first
+/// `target_tokens` and `reference_tokens` are the number of words of each corpus
+pub fn calculate_keyness(target: &HashMap<String, usize>, target_tokens: usize, reference: &HashMap<String, usize>, reference_tokens: usize, min_frequency: usize) -> Vec<Keyword> {
+    let target_total = target_tokens as f64;
+    let reference_total = reference_tokens as f64;
+    if target_total == 0.0 || reference_total == 0.0 {
+        return Vec::new();
+    }
+
+    let mut result: Vec<Keyword> = target
+        .iter()
+        .filter(|(word, frequency)| **frequency + reference.get(*word).copied().unwrap_or(0) >= min_frequency)
+        .map(|(word, frequency)| score(word, *frequency, reference.get(word).copied().unwrap_or(0), target_total, reference_total))
+        .collect();
+    result.sort_by(|a, b| b.log_likelihood.partial_cmp(&a.log_likelihood).unwrap().then_with(|| a.word.cmp(&b.word)));
+    result
+}
+
+// read the first column (words) and the last column (totals) of a frequency table, skipping the header
+// the table is read with the same delimiter as the tables written by the tool, the rows of the target phrases are left out
+fn read_frequency_table(path: &Path, delimiter: u8, phrases: &[String]) -> Result<HashMap<String, usize>> {
+    let file = fs::File::open(path).map_err(|source| Error::Io { path: path.to_path_buf(), source })?;
+    let mut reader = csv::ReaderBuilder::new().delimiter(delimiter).flexible(true).from_reader(file);
+    let mut result: HashMap<String, usize> = HashMap::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(|error| Error::Reference { path: path.to_path_buf(), reason: error.to_string() })?;
+        // line numbers start at 1, after the header
+        let line = i + 2;
+        let (word, total) = match (record.get(0), record.get(record.len().saturating_sub(1))) {
+            (Some(word), Some(total)) if record.len() > 1 => (word, total),
+            _ if record.iter().all(str::is_empty) => continue,
+            _ => return Err(Error::Reference { path: path.to_path_buf(), reason: format!("line {} has no frequency column", line) }),
+        };
+        let total: usize = total.trim().parse().map_err(|_| Error::Reference {
+            path: path.to_path_buf(),
+            reason: format!("line {}: \"{}\" is not a frequency", line, total),
+        })?;
+        if !phrases.contains(&word.to_string()) {
+            *result.entry(word.to_string()).or_insert(0) += total;
+        }
+    }
+    Ok(result)
+}
+
+/// Word totals and number of words of the reference: a folder is digested and counted with the same settings as the target, a file is read as a frequency table
+pub fn load_reference(reference: &Path, settings: &Settings) -> Result<(HashMap<String, usize>, usize)> {
+    if reference.is_dir() {
+        let files = files::discover_files(reference, &settings.include, &settings.exclude)?;
+        let tokenizer = tokenizer::from_settings(&settings.tokenizer);
+        let corpus = utils::digest_files(reference, &files, settings, tokenizer.as_ref())?;
+        let frequencies = crate::analyses::word_frequency::count(&corpus, &settings.phrases, settings.normalization.report);
+        Ok((word_totals(&frequencies, &settings.phrases), corpus.token_count()))
+    }
+    else {
+        let totals = read_frequency_table(reference, settings.output.delimiter_byte(), &settings.phrases)?;
+        let tokens = totals.values().sum();
+        Ok((totals, tokens))
+    }
+}
+
+// main compare the target with the reference, and write the significant positive and negative keywords
+pub fn main(paths: &OutputPaths, corpus: &Corpus, frequencies: &FrequencyTable, settings: &Settings) -> Result<()> {
+    println!("------------------------------------------------------------");
+    println!("ANALYZING KEYNESS...");
+    // start time for the whole function
+    let start_total = std::time::Instant::now();
+
+    let keyness_settings = &settings.keyness;
+    if keyness_settings.reference.is_empty() {
+        return Err(Error::Settings { path: PathBuf::new(), reason: "keyness needs a reference folder or frequency table (\"keyness\": { \"reference\": ... })".to_string() });
+    }
+    let critical_value = critical_value(keyness_settings.p_value).map_err(|reason| Error::Settings { path: PathBuf::new(), reason })?;
+
+    let start = std::time::Instant::now();
+    let reference_path = Path::new(&keyness_settings.reference);
+    println!("Reference: {}", reference_path.display());
+    let (reference, reference_tokens) = load_reference(reference_path, settings)?;
+    let duration = start.elapsed();
+    println!("\x1b[2m  Reference of {} unique words loaded in {} ms\x1b[0m", reference.len(), duration.as_millis());
+
+    let target = word_totals(frequencies, &settings.phrases);
+    let keywords = calculate_keyness(&target, corpus.token_count(), &reference, reference_tokens, keyness_settings.min_frequency);
+    let (positive, negative): (Vec<Keyword>, Vec<Keyword>) = keywords
+        .into_iter()
+        .filter(|keyword| keyword.log_likelihood >= critical_value)
+        .partition(Keyword::is_positive);
+    println!("\x1b[2m  {} positive and {} negative keywords (p < {})\x1b[0m", positive.len(), negative.len(), keyness_settings.p_value);
+
+    utils::create_output_folder(&paths.directory)?;
+    let csv_string = tables::keywords_to_csv_string(&positive, &settings.output)?;
+    let output_file_path = paths.file("keyness_positive", "csv");
+    utils::write_output_file(&output_file_path, csv_string.as_bytes())?;
+
+    let csv_string = tables::keywords_to_csv_string(&negative, &settings.output)?;
+    let output_file_path = paths.file("keyness_negative", "csv");
+    utils::write_output_file(&output_file_path, csv_string.as_bytes())?;
+
+    // end time
+    let duration = start_total.elapsed();
+    println!("Keyness analysis completed in {} ms", duration.as_millis());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyses::test_util::assert_close;
+
+    fn totals(counts: &[(&str, usize)]) -> HashMap<String, usize> {
+        counts.iter().map(|(word, count)| (word.to_string(), *count)).collect()
+    }
+
+    #[test]
+    fn scores() {
+        // a = 10 of c = 100 words, b = 10 of d = 1000 words: E1 = 100 * 20 / 1100, E2 = 1000 * 20 / 1100
+        let target = totals(&[("gold", 10), ("the", 90)]);
+        let reference = totals(&[("gold", 10), ("the", 990)]);
+        let keywords = calculate_keyness(&target, 100, &reference, 1000, 5);
+        let gold = keywords.iter().find(|keyword| keyword.word == "gold").unwrap();
+
+        assert_close(gold.log_likelihood, 2.0 * (10.0 * 5.5f64.ln() + 10.0 * 0.55f64.ln()), 1e-6);
+        assert_close(gold.log_likelihood, 22.138222, 1e-6);
+        // 1100 * (10 * 990 - 10 * 90)² / (20 * 1080 * 100 * 1000)
+        assert_close(gold.chi_squared, 41.25, 1e-6);
+        assert_close(gold.target_per_million, 100_000.0, 1e-6);
+        assert_close(gold.reference_per_million, 10_000.0, 1e-6);
+        assert_close(gold.percent_diff, 900.0, 1e-6);
+        assert_close(gold.log_ratio, 10f64.log2(), 1e-6);
+        assert!(gold.is_positive());
+        assert_eq!(keywords[0].word, "gold");
+    }
+
+    #[test]
+    fn phrases_are_not_words() {
+        let chapter = totals(&[("gold", 2), ("ring", 2), ("gold ring", 2)]);
+        let frequencies = FrequencyTable { files: HashMap::from([("a.txt".to_string(), vec![chapter])]) };
+        assert_eq!(word_totals(&frequencies, &["gold ring".to_string()]), totals(&[("gold", 2), ("ring", 2)]));
+    }
+
+    #[test]
+    fn saved_table_without_phrase_rows() {
+        let path = std::env::temp_dir().join(format!("keyness_reference_{}.csv", std::process::id()));
+        fs::write(&path, "Words,a.txt,Total\ngold,3,3\nring,4,4\ngold ring,2,2\n\n").unwrap();
+        let table = read_frequency_table(&path, b',', &["gold ring".to_string()]);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(table.unwrap(), totals(&[("gold", 3), ("ring", 4)]));
+    }
+}
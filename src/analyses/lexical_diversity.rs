@@ -0,0 +1,229 @@
+use std::collections::{HashMap, HashSet};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{tables, utils};
+use crate::corpus::Corpus;
+use crate::error::Result;
+use crate::output::OutputPaths;
+use crate::settings::Settings;
+use crate::normalizer::TermForm;
+
+/// The "lexical_diversity" section of settings.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LexicalDiversitySettings {
+    /// TTR at which an MTLD factor ends
+    pub mtld_threshold: f64,
+    /// Number of tokens drawn for HD-D
+    pub hdd_sample_size: usize,
+}
+
+impl Default for LexicalDiversitySettings {
+    fn default() -> Self {
+        LexicalDiversitySettings {
+            mtld_threshold: 0.72,
+            hdd_sample_size: 42,
+        }
+    }
+}
+
+/// Vocabulary statistics of a chapter, a file or the whole corpus
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexicalDiversity {
+    /// File name, or "Corpus Total"
+    pub file: String,
+    /// Chapter number (starting at 1), or None for the whole file or corpus
+    pub chapter: Option<usize>,
+    pub tokens: usize,
+    pub types: usize,
+    /// Type-token ratio
+    pub ttr: f64,
+    /// Guiraud's root TTR, types / √tokens
+    pub root_ttr: f64,
+    /// Number of words seen once
+    pub hapax_legomena: usize,
+    /// Number of words seen twice
+    pub dis_legomena: usize,
+    pub yules_k: f64,
+    /// Measure of textual lexical diversity (McCarthy & Jarvis), average of the forward and backward passes
+    pub mtld: f64,
+    /// Hypergeometric distribution D, None if there are fewer tokens than the sample size
+    pub hd_d: Option<f64>,
+}
+
+// one MTLD pass: number of factors (stretches of text whose TTR falls to the threshold), counting the last partial one
+fn mtld_factors<'a>(tokens: impl Iterator<Item = &'a String>, threshold: f64) -> f64 {
+    let mut factors = 0.0;
+    let mut types: HashSet<&String> = HashSet::new();
+    let mut count = 0;
+    for token in tokens {
+        types.insert(token);
+        count += 1;
+        if types.len() as f64 / count as f64 <= threshold {
+            factors += 1.0;
+            types.clear();
+            count = 0;
+        }
+    }
+    if count > 0 {
+        let ttr = types.len() as f64 / count as f64;
+        factors += (1.0 - ttr) / (1.0 - threshold);
+    }
+    factors
+}
+
+fn mtld(tokens: &[&String], threshold: f64) -> f64 {
+    let pass = |factors: f64| if factors > 0.0 { tokens.len() as f64 / factors } else { tokens.len() as f64 };
+    let forward = pass(mtld_factors(tokens.iter().copied(), threshold));
+    let backward = pass(mtld_factors(tokens.iter().rev().copied(), threshold));
+    (forward + backward) / 2.0
+}
+
+// sum over the types of (probability of drawing the type at least once in a sample) / sample size
+fn hd_d(frequencies: &HashMap<&String, usize>, tokens: usize, sample_size: usize) -> Option<f64> {
+    if sample_size == 0 || tokens < sample_size {
+        return None;
+    }
+    let n = tokens as f64;
+    let sum: f64 = frequencies
+        .values()
+        .map(|frequency| {
+            // hypergeometric probability of drawing none of the `frequency` occurrences
+            let mut none = 1.0;
+            for i in 0..sample_size {
+                let remaining = n - *frequency as f64 - i as f64;
+                if remaining <= 0.0 {
+                    none = 0.0;
+                    break;
+                }
+                none *= remaining / (n - i as f64);
+            }
+            1.0 - none
+        })
+        .sum();
+    Some(sum / sample_size as f64)
+}
+
+fn measure(file: &str, chapter: Option<usize>, tokens: &[&String], settings: &LexicalDiversitySettings) -> LexicalDiversity {
+    let mut frequencies: HashMap<&String, usize> = HashMap::new();
+    for token in tokens {
+        *frequencies.entry(*token).or_insert(0) += 1;
+    }
+    let n = tokens.len() as f64;
+    let types = frequencies.len();
+
+    // Yule's K = 10^4 * (Σ m² * V(m) - N) / N², V(m) being the number of types seen m times
+    let sum_squares: f64 = frequencies.values().map(|m| (*m as f64).powi(2)).sum();
+    let yules_k = if tokens.is_empty() { 0.0 } else { 10_000.0 * (sum_squares - n) / (n * n) };
+
+    LexicalDiversity {
+        file: file.to_string(),
+        chapter,
+        tokens: tokens.len(),
+        types,
+        ttr: if tokens.is_empty() { 0.0 } else { types as f64 / n },
+        root_ttr: if tokens.is_empty() { 0.0 } else { types as f64 / n.sqrt() },
+        hapax_legomena: frequencies.values().filter(|m| **m == 1).count(),
+        dis_legomena: frequencies.values().filter(|m| **m == 2).count(),
+        yules_k,
+        mtld: mtld(tokens, settings.mtld_threshold),
+        hd_d: hd_d(&frequencies, tokens.len(), settings.hdd_sample_size),
+    }
+}
+
+/// Vocabulary statistics of every chapter, of every file with chapters, and of the whole corpus (last row)
+pub fn calculate_lexical_diversity(corpus: &Corpus, form: TermForm, settings: &LexicalDiversitySettings) -> Vec<LexicalDiversity> {
+    let start = std::time::Instant::now();
+    let mut documents: Vec<_> = corpus.documents.iter().collect();
+    documents.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut result: Vec<LexicalDiversity> = documents
+        .par_iter()
+        .flat_map(|document| {
+            let mut rows: Vec<LexicalDiversity> = document.chapters
+                .iter()
+                .enumerate()
+                .map(|(i, chapter)| measure(&document.name, Some(i + 1), &chapter.terms(form).iter().collect::<Vec<&String>>(), settings))
+                .collect();
+            if document.chapters.len() > 1 {
+                let tokens: Vec<&String> = document.chapters.iter().flat_map(|chapter| chapter.terms(form)).collect();
+                rows.push(measure(&document.name, None, &tokens, settings));
+            }
+            rows
+        })
+        .collect();
+
+    let tokens: Vec<&String> = documents.iter().flat_map(|document| &document.chapters).flat_map(|chapter| chapter.terms(form)).collect();
+    result.push(measure("Corpus Total", None, &tokens, settings));
+
+    let duration = start.elapsed();
+    println!("\x1b[2m  Lexical diversity of {} document units calculated in {} ms\x1b[0m", result.len(), duration.as_millis());
+
+    result
+}
+
+// main calculate the vocabulary statistics and write the summary table
+pub fn main(paths: &OutputPaths, corpus: &Corpus, settings: &Settings) -> Result<()> {
+    println!("------------------------------------------------------------");
+    println!("ANALYZING LEXICAL DIVERSITY...");
+    // start time for the whole function
+    let start_total = std::time::Instant::now();
+
+    let rows = calculate_lexical_diversity(corpus, settings.normalization.report, &settings.lexical_diversity);
+
+    utils::create_output_folder(&paths.directory)?;
+    let csv_string = tables::lexical_diversity_to_csv_string(&rows, &settings.output)?;
+    let output_file_path = paths.file("lexical-diversity", "csv");
+    utils::write_output_file(&output_file_path, csv_string.as_bytes())?;
+
+    // end time
+    let duration = start_total.elapsed();
+    println!("Lexical diversity analysis completed in {} ms", duration.as_millis());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyses::test_util::{assert_close, words};
+
+    fn measure_text(text: &str, settings: &LexicalDiversitySettings) -> LexicalDiversity {
+        let words = words(text);
+        measure("a.txt", Some(1), &words.iter().collect::<Vec<&String>>(), settings)
+    }
+
+    #[test]
+    fn counts_ttr_and_yules_k() {
+        let row = measure_text("a a b c", &LexicalDiversitySettings::default());
+        assert_eq!((row.tokens, row.types, row.hapax_legomena, row.dis_legomena), (4, 3, 2, 1));
+        assert_close(row.ttr, 0.75, 1e-6);
+        assert_close(row.root_ttr, 1.5, 1e-6);
+        // 10^4 * (2² + 1² + 1² - 4) / 4²
+        assert_close(row.yules_k, 1250.0, 1e-6);
+    }
+
+    #[test]
+    fn mtld_full_and_partial_factors() {
+        let even = words("a b a b a b");
+        let tokens: Vec<&String> = even.iter().collect();
+        // the TTR falls to 2/3 after "a b a", twice in each direction: 6 tokens / 2 factors
+        assert_close(mtld(&tokens, 0.72), 3.0, 1e-6);
+
+        // no full factor: the partial one counts (1 - 0.75) / (1 - 0.72)
+        let partial = words("a b c a");
+        let tokens: Vec<&String> = partial.iter().collect();
+        assert_close(mtld(&tokens, 0.72), 4.0 / (0.25 / 0.28), 1e-6);
+    }
+
+    #[test]
+    fn hd_d_sample() {
+        let settings = LexicalDiversitySettings { hdd_sample_size: 2, ..LexicalDiversitySettings::default() };
+        // each of the 2 types is missed by a sample of 2 out of 4 with probability 2/4 * 1/3
+        let row = measure_text("a a b b", &settings);
+        assert_close(row.hd_d.unwrap(), 2.0 * (1.0 - 1.0 / 6.0) / 2.0, 1e-6);
+
+        assert_eq!(measure_text("a b", &LexicalDiversitySettings::default()).hd_d, None);
+    }
+}
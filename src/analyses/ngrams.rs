@@ -0,0 +1,148 @@
+use std::collections::{HashMap, HashSet};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::tables;
+use crate::utils::utils;
+use crate::corpus::{ChapterFrequencies, Corpus, FrequencyTable};
+use crate::error::Result;
+use crate::output::OutputPaths;
+use crate::settings::Settings;
+use crate::normalizer::TermForm;
+
+/// The "ngrams" section of settings.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NgramSettings {
+    /// Smallest n to count (2 = bigrams)
+    pub min_n: usize,
+    /// Largest n to count (3 = trigrams)
+    pub max_n: usize,
+    /// N-grams seen fewer times than this in the whole corpus are left out of the tables
+    pub min_count: usize,
+    /// Leave out n-grams that start or end with a stopword, such as "of the" or "the house of"
+    pub stopword_boundary: bool,
+}
+
+impl Default for NgramSettings {
+    fn default() -> Self {
+        NgramSettings {
+            min_n: 2,
+            max_n: 3,
+            min_count: 2,
+            stopword_boundary: true,
+        }
+    }
+}
+
+// n-grams never cross chapter boundaries, the words of an n-gram are joined by a single space
+fn chapter_ngrams(words: &[String], n: usize, stopword_boundary: bool) -> HashMap<String, usize> {
+    let mut result: HashMap<String, usize> = HashMap::new();
+    if n == 0 {
+        return result;
+    }
+    for window in words.windows(n) {
+        if stopword_boundary && (utils::is_stopword_any_case(&window[0]) || utils::is_stopword_any_case(&window[n - 1])) {
+            continue;
+        }
+        *result.entry(window.join(" ")).or_insert(0) += 1;
+    }
+    result
+}
+
+/// Count the n-grams of length `n` in each chapter of each file, then drop those seen fewer than `min_count` times in the whole corpus
+pub fn count(corpus: &Corpus, n: usize, form: TermForm, min_count: usize, stopword_boundary: bool) -> FrequencyTable {
+    let mut files: ChapterFrequencies<usize> = corpus.documents
+        .par_iter()
+        .map(|document| {
+            let chapters: Vec<HashMap<String, usize>> = document.chapters
+                .iter()
+                .map(|chapter| chapter_ngrams(chapter.terms(form), n, stopword_boundary))
+                .collect();
+            (document.name.clone(), chapters)
+        })
+        .collect();
+
+    if min_count > 1 {
+        let mut totals: HashMap<String, usize> = HashMap::new();
+        for chapters in files.values() {
+            for chapter in chapters {
+                for (ngram, count) in chapter {
+                    *totals.entry(ngram.clone()).or_insert(0) += count;
+                }
+            }
+        }
+        for chapters in files.values_mut() {
+            for chapter in chapters {
+                chapter.retain(|ngram, _| totals.get(ngram).is_some_and(|total| *total >= min_count));
+            }
+        }
+    }
+
+    FrequencyTable { files }
+}
+
+// main count the n-grams for every n in the configured range and write one table per n
+pub fn main(paths: &OutputPaths, corpus: &Corpus, settings: &Settings) -> Result<()> {
+    println!("------------------------------------------------------------");
+    println!("ANALYZING N-GRAMS...");
+    // start time for the whole function
+    let start_total = std::time::Instant::now();
+
+    let ngram_settings = &settings.ngrams;
+    utils::create_output_folder(&paths.directory)?;
+
+    for n in ngram_settings.min_n..=ngram_settings.max_n {
+        let start = std::time::Instant::now();
+        let frequencies = count(corpus, n, settings.normalization.report, ngram_settings.min_count, ngram_settings.stopword_boundary);
+        let simple_ngram_freq_map = frequencies.file_totals();
+        let file_names = frequencies.file_names();
+        let ngrams: HashSet<String> = simple_ngram_freq_map.values().flat_map(|ngram_freq| ngram_freq.keys().cloned()).collect();
+        let duration = start.elapsed();
+        println!("\x1b[2m  Counted {} unique {}-grams in {} ms\x1b[0m", ngrams.len(), n, duration.as_millis());
+
+        let csv_string = tables::combined_file_map_to_csv_string_usize(&file_names, &ngrams, &simple_ngram_freq_map, &[], &settings.output)?;
+        let output_file_path = paths.file(&format!("ngrams_{}", n), "csv");
+        utils::write_output_file(&output_file_path, csv_string.as_bytes())?;
+    }
+
+    // end time
+    let duration = start_total.elapsed();
+    println!("N-gram analysis completed in {} ms", duration.as_millis());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyses::test_util::{corpus, document, words};
+
+    #[test]
+    fn bigrams_and_trigrams() {
+        let bigrams = chapter_ngrams(&words("red fish blue fish red fish"), 2, false);
+        assert_eq!(bigrams.len(), 4);
+        assert_eq!((bigrams["red fish"], bigrams["fish blue"], bigrams["blue fish"], bigrams["fish red"]), (2, 1, 1, 1));
+        let trigrams = chapter_ngrams(&words("a b c d"), 3, false);
+        assert_eq!(trigrams, HashMap::from([("a b c".to_string(), 1), ("b c d".to_string(), 1)]));
+        assert!(chapter_ngrams(&words("a b"), 3, false).is_empty());
+        assert!(chapter_ngrams(&words("a b"), 0, false).is_empty());
+    }
+
+    #[test]
+    fn stopword_boundary() {
+        let ngrams = chapter_ngrams(&words("The house of cards fell"), 3, true);
+        // "The house of" starts with a stop word, "house of cards" keeps its stop word in the middle
+        assert_eq!(ngrams, HashMap::from([("house of cards".to_string(), 1)]));
+    }
+
+    #[test]
+    fn chapters_and_min_count() {
+        let corpus = corpus(vec![document("a", &["red fish blue", "fish red fish"]), document("b", &["red fish"])]);
+        let table = count(&corpus, 2, TermForm::Raw, 2, false);
+        // "blue fish" would cross the chapter boundary; only "red fish" (3 times) reaches min_count 2
+        assert_eq!(table.files["a"][0], HashMap::from([("red fish".to_string(), 1)]));
+        assert_eq!(table.files["a"][1], HashMap::from([("red fish".to_string(), 1)]));
+        assert_eq!(table.files["b"][0], HashMap::from([("red fish".to_string(), 1)]));
+    }
+}
@@ -0,0 +1,289 @@
+use std::path::Path;
+use std::collections::HashMap;
+use serde_json::{json, Value};
+
+use crate::analyses::{charts, wordcloud, zipf};
+use crate::utils::{graph, utils, wordcloud as cloud};
+use crate::corpus::{Corpus, FrequencyTable, TfIdfTable};
+use crate::error::Result;
+use crate::output::OutputPaths;
+use crate::settings::Settings;
+
+const REPORT_CSS: &str = r#"
+body { font-family: Helvetica, Arial, sans-serif; margin: 0 auto; max-width: 1100px; padding: 0 20px 40px; color: #222; }
+h1 { margin-top: 28px; }
+h2 { border-bottom: 2px solid #4e79a7; padding-bottom: 4px; margin-top: 40px; }
+table { border-collapse: collapse; font-size: 13px; }
+th, td { border: 1px solid #ddd; padding: 3px 8px; }
+th { background: #f0f3f7; cursor: pointer; user-select: none; white-space: nowrap; position: sticky; top: 0; }
+td.number { text-align: right; font-variant-numeric: tabular-nums; }
+.table-wrapper { max-height: 520px; overflow: auto; border: 1px solid #ddd; }
+.controls { margin: 8px 0; display: flex; gap: 12px; align-items: center; flex-wrap: wrap; font-size: 13px; }
+.controls input[type=search] { padding: 4px 8px; width: 260px; }
+.chart { margin: 12px 0; overflow-x: auto; }
+dl.metadata { display: grid; grid-template-columns: max-content auto; gap: 4px 16px; }
+dl.metadata dt { font-weight: bold; }
+pre { background: #f6f8fa; padding: 10px; overflow: auto; font-size: 12px; }
+"#;
+
+// sortable, searchable tables built from the embedded data, showing 100 rows at a time
+const REPORT_JS: &str = r#"
+function buildTable(container, table) {
+  let rows = table.rows, sortColumn = -1, ascending = true, shown = 100;
+  const controls = document.createElement('div'); controls.className = 'controls';
+  const search = document.createElement('input'); search.type = 'search'; search.placeholder = 'Search ' + table.columns[0].toLowerCase() + '...';
+  const count = document.createElement('span');
+  controls.append(search, count);
+  // a Set, so that words such as "constructor" are not found on the object prototype
+  const stopwords = new Set(table.stopwords || []);
+  let hideStopwords = null;
+  if (table.stopwords) {
+    const label = document.createElement('label'); hideStopwords = document.createElement('input'); hideStopwords.type = 'checkbox'; hideStopwords.checked = true;
+    label.append(hideStopwords, ' Hide stop words'); controls.append(label);
+    hideStopwords.addEventListener('change', () => { shown = 100; render(); });
+  }
+  const wrapper = document.createElement('div'); wrapper.className = 'table-wrapper';
+  const element = document.createElement('table'); wrapper.append(element);
+  const more = document.createElement('button'); more.textContent = 'Show more';
+  more.addEventListener('click', () => { shown += 500; render(); });
+  container.append(controls, wrapper, more);
+  search.addEventListener('input', () => { shown = 100; render(); });
+
+  function render() {
+    const query = search.value.toLowerCase();
+    const filtered = rows.filter(row => String(row[0]).toLowerCase().includes(query) && !(hideStopwords && hideStopwords.checked && stopwords.has(row[0])));
+    element.replaceChildren();
+    const header = element.createTHead().insertRow();
+    table.columns.forEach((column, i) => {
+      const th = document.createElement('th');
+      th.textContent = column + (i === sortColumn ? (ascending ? ' ▲' : ' ▼') : '');
+      th.addEventListener('click', () => {
+        ascending = sortColumn === i ? !ascending : i === 0;
+        sortColumn = i;
+        rows = rows.slice().sort((a, b) => {
+          const order = typeof a[i] === 'number' ? a[i] - b[i] : String(a[i]).localeCompare(String(b[i]));
+          return ascending ? order : -order;
+        });
+        render();
+      });
+      header.append(th);
+    });
+    const body = element.createTBody();
+    filtered.slice(0, shown).forEach(row => {
+      const tr = body.insertRow();
+      row.forEach(value => {
+        const td = tr.insertCell();
+        if (typeof value === 'number') { td.className = 'number'; td.textContent = Number.isInteger(value) ? value : value.toPrecision(4); }
+        else { td.textContent = value; }
+      });
+    });
+    count.textContent = Math.min(shown, filtered.length) + ' of ' + filtered.length + ' rows';
+    more.style.display = filtered.length > shown ? '' : 'none';
+  }
+  render();
+}
+document.querySelectorAll('[data-table]').forEach(container => buildTable(container, REPORT_DATA[container.dataset.table]));
+"#;
+
+// <word, [count in each file..., total]> as table rows, most frequent first
+fn count_rows(file_names: &[String], file_totals: &HashMap<String, HashMap<String, usize>>, words: impl Iterator<Item = String>) -> Vec<Value> {
+    let mut rows: Vec<(String, Vec<usize>, usize)> = words
+        .map(|word| {
+            let counts: Vec<usize> = file_names.iter().map(|file_name| file_totals[file_name].get(&word).copied().unwrap_or(0)).collect();
+            let total = counts.iter().sum();
+            (word, counts, total)
+        })
+        .collect();
+    rows.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+    rows.into_iter()
+        .map(|(word, counts, total)| {
+            let mut row = vec![json!(word)];
+            row.extend(counts.into_iter().map(|count| json!(count)));
+            row.push(json!(total));
+            Value::Array(row)
+        })
+        .collect()
+}
+
+fn table(columns: Vec<String>, rows: Vec<Value>) -> Value {
+    json!({ "columns": columns, "rows": rows })
+}
+
+fn section(title: &str, id: &str, body: &str) -> String {
+    format!("<section id=\"{}\">\n<h2>{}</h2>\n{}\n</section>\n", id, graph::escape(title), body)
+}
+
+/// Build the whole report: run metadata, tables (word frequency, phrases, TF-IDF) and charts, with inline styles and scripts
+/// `tfidf` is the TF-IDF without stopwords (empty if there is a single document), `zipf_fits` come from [`zipf::calculate_zipf`]
+pub fn build_report(folder_name: &str, input_folder: &Path, corpus: &Corpus, frequencies: &FrequencyTable, tfidf: &TfIdfTable, zipf_fits: &[zipf::ZipfFit], settings: &Settings) -> Result<String> {
+    let file_names = frequencies.file_names();
+    let file_totals = frequencies.file_totals();
+    let phrases = &settings.phrases;
+    let mut data = serde_json::Map::new();
+    let mut sections = String::new();
+
+    // metadata
+    let tokens: usize = corpus.documents.iter().flat_map(|document| &document.chapters).map(|chapter| chapter.tokens).sum();
+    let mut metadata = String::from("<dl class=\"metadata\">\n");
+    for (name, value) in [
+        ("Input folder", input_folder.display().to_string()),
+        ("Generated", chrono::Local::now().format("%Y-%m-%d %H:%M:%S %:z").to_string()),
+        ("Version", format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))),
+        ("Files", corpus.documents.len().to_string()),
+        ("Chapters", frequencies.document_count().to_string()),
+        ("Words (tokens)", tokens.to_string()),
+        ("Unique words", corpus.normalized_vocabulary.len().to_string()),
+    ] {
+        metadata.push_str(&format!("<dt>{}</dt><dd>{}</dd>\n", name, graph::escape(&value)));
+    }
+    metadata.push_str("</dl>\n<h3>Files</h3>\n<ul>\n");
+    for document in &corpus.documents {
+        metadata.push_str(&format!("<li>{} ({} chapters, {})</li>\n", graph::escape(&document.name), document.chapters.len(), graph::escape(&document.encoding)));
+    }
+    let settings_json = serde_json::to_string_pretty(settings).unwrap_or_default();
+    metadata.push_str(&format!("</ul>\n<details><summary>Settings</summary>\n<pre>{}</pre>\n</details>", graph::escape(&settings_json)));
+    sections.push_str(&section("Run", "run", &metadata));
+
+    // word frequency, with the stopwords flagged so that they can be hidden
+    let mut columns: Vec<String> = vec!["Words".to_string()];
+    columns.extend(file_names.iter().cloned());
+    columns.push("Total".to_string());
+    let words = corpus_words(&file_totals).into_iter().filter(|word| !phrases.contains(word));
+    let mut word_table = table(columns.clone(), count_rows(&file_names, &file_totals, words));
+    let stopwords: Vec<Value> = corpus_words(&file_totals).into_iter().filter(|word| utils::is_stopword(word)).map(Value::String).collect();
+    word_table["stopwords"] = Value::Array(stopwords);
+    data.insert("words".to_string(), word_table);
+    sections.push_str(&section("Word frequency", "word-frequency", "<div data-table=\"words\"></div>"));
+
+    if !phrases.is_empty() {
+        let mut columns = columns.clone();
+        columns[0] = "Phrases".to_string();
+        data.insert("phrases".to_string(), table(columns, count_rows(&file_names, &file_totals, phrases.iter().cloned())));
+        sections.push_str(&section("Target phrases", "phrases", "<div data-table=\"phrases\"></div>"));
+    }
+
+    // TF-IDF, summed over the chapters of each file
+    let without_stopwords = frequencies.without_stopwords();
+    if !tfidf.is_empty() {
+        let mut rows: Vec<(String, Vec<f64>, f64)> = tfidf.words()
+            .into_iter()
+            .map(|word| {
+                let values: Vec<f64> = file_names.iter().map(|file_name| tfidf.files[file_name].iter().filter_map(|chapter| chapter.get(&word)).sum()).collect();
+                let total = values.iter().sum();
+                (word, values, total)
+            })
+            .collect();
+        rows.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap().then_with(|| a.0.cmp(&b.0)));
+        let rows = rows.into_iter()
+            .map(|(word, values, total)| {
+                let mut row = vec![json!(word)];
+                row.extend(values.into_iter().map(|value| json!(value)));
+                row.push(json!(total));
+                Value::Array(row)
+            })
+            .collect();
+        data.insert("tfidf".to_string(), table(columns.clone(), rows));
+        let heatmap = charts::tf_idf_heatmap(tfidf, settings.charts.heatmap_words);
+        let body = format!("<p>TF-IDF of the words (stop words removed), summed over the chapters of each file.</p>\n<div data-table=\"tfidf\"></div>\n<div class=\"chart\">{}</div>", heatmap);
+        sections.push_str(&section("TF-IDF", "tf-idf", &body));
+    }
+
+    // charts
+    let bars = charts::top_words(&without_stopwords.corpus_totals(), settings.charts.top_n);
+    let bar_chart = graph::bar_chart(&format!("Top {} words (no stop words)", bars.len()), &bars, graph::PALETTE[0]);
+    let zipf_chart = charts::zipf_chart(zipf_fits);
+    let cloud_settings = &settings.wordcloud;
+    let weights: HashMap<String, f64> = without_stopwords.corpus_totals().into_iter().map(|(word, count)| (word, count as f64)).collect();
+    let font = cloud::load_font(&cloud_settings.font_file)?;
+    let placed = cloud::layout(
+        &wordcloud::font_sizes(&weights, cloud_settings),
+        cloud_settings.width as f32, cloud_settings.height as f32, cloud_settings.min_font_size, &cloud_settings.palette, Some(&font),
+    );
+    let word_cloud = cloud::to_svg(&placed, cloud_settings.width as f32, cloud_settings.height as f32, &cloud_settings.background);
+    let body = format!("<div class=\"chart\">{}</div>\n<div class=\"chart\">{}</div>\n<div class=\"chart\">{}</div>", bar_chart, word_cloud, zipf_chart);
+    sections.push_str(&section("Charts", "charts", &body));
+
+    // "</" would end the script element early
+    let data_json = serde_json::to_string(&Value::Object(data)).unwrap_or_default().replace("</", "<\\/");
+
+    Ok(format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{css}</style>\n</head>\n<body>\n<h1>{title}</h1>\n<nav><a href=\"#run\">Run</a> · <a href=\"#word-frequency\">Word frequency</a>{phrases_link}{tfidf_link} · <a href=\"#charts\">Charts</a></nav>\n{sections}<script>\nconst REPORT_DATA = {data};\n{js}</script>\n</body>\n</html>\n",
+        title = graph::escape(&format!("Word frequency analysis: {}", folder_name)),
+        css = REPORT_CSS,
+        phrases_link = if phrases.is_empty() { "" } else { " · <a href=\"#phrases\">Target phrases</a>" },
+        tfidf_link = if !tfidf.is_empty() { " · <a href=\"#tf-idf\">TF-IDF</a>" } else { "" },
+        sections = sections,
+        data = data_json,
+        js = REPORT_JS,
+    ))
+}
+
+// all the words (and phrases) of the table
+fn corpus_words(file_totals: &HashMap<String, HashMap<String, usize>>) -> Vec<String> {
+    let mut words: Vec<String> = file_totals.values().flat_map(|map| map.keys().cloned()).collect();
+    words.sort();
+    words.dedup();
+    words
+}
+
+// main write the html report, a single file with everything inline so that it works offline and can be emailed
+pub fn main(paths: &OutputPaths, input_folder: &Path, corpus: &Corpus, frequencies: &FrequencyTable, tfidf: &TfIdfTable, zipf_fits: &[zipf::ZipfFit], settings: &Settings) -> Result<()> {
+    println!("------------------------------------------------------------");
+    println!("GENERATING HTML REPORT...");
+    // start time for the whole function
+    let start_total = std::time::Instant::now();
+
+    let html = build_report(&paths.folder_name, input_folder, corpus, frequencies, tfidf, zipf_fits, settings)?;
+    utils::create_output_folder(&paths.directory)?;
+    let output_file_path = paths.file("report", "html");
+    utils::write_output_file(&output_file_path, html.as_bytes())?;
+
+    // end time
+    let duration = start_total.elapsed();
+    println!("HTML report ({} KB) completed in {} ms", html.len() / 1024, duration.as_millis());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyses::test_util::{corpus, document};
+    use crate::normalizer::TermForm;
+
+    // the data embedded in the report's script
+    fn report_data(html: &str) -> Value {
+        let start = html.find("const REPORT_DATA = ").unwrap() + "const REPORT_DATA = ".len();
+        let end = start + html[start..].find(";\n").unwrap();
+        serde_json::from_str(&html[start..end]).unwrap()
+    }
+
+    #[test]
+    fn small_corpus() {
+        let corpus = corpus(vec![
+            document("a</script>.txt", &["the dragon said end</script> and slept"]),
+            document("b.txt", &["the dragon woke", "the knight fled"]),
+        ]);
+        let settings = Settings { phrases: vec!["end</script>".to_string()], ..Settings::default() };
+        let frequencies = crate::count(&corpus, &settings.phrases, TermForm::Normalized);
+
+        let html = build_report("books", Path::new("books"), &corpus, &frequencies, &TfIdfTable::default(), &[], &settings).unwrap();
+        // only the end of the script element itself
+        assert_eq!(html.matches("</script>").count(), 1);
+        assert!(html.contains("a<\\/script>.txt") && html.contains("end<\\/script>"));
+        assert!(!html.contains("id=\"tf-idf\"") && !html.contains("href=\"#tf-idf\""));
+
+        let data = report_data(&html);
+        assert_eq!(data["words"]["columns"], json!(["Words", "a</script>.txt", "b.txt", "Total"]));
+        assert_eq!(data["phrases"]["rows"], json!([["end</script>", 1, 0, 1]]));
+        assert!(data.get("tfidf").is_none());
+        let stopwords = data["words"]["stopwords"].as_array().unwrap();
+        assert!(stopwords.contains(&json!("the")) && stopwords.contains(&json!("and")));
+        assert!(!stopwords.contains(&json!("dragon")));
+
+        let tfidf = crate::tf_idf(&frequencies.without_stopwords());
+        let html = build_report("books", Path::new("books"), &corpus, &frequencies, &tfidf, &[], &settings).unwrap();
+        assert!(html.contains("id=\"tf-idf\"") && html.contains("href=\"#tf-idf\""));
+        assert!(report_data(&html).get("tfidf").is_some());
+    }
+}
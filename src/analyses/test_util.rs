@@ -0,0 +1,51 @@
+// fixtures and assertions shared by the unit tests of the analyses
+
+use std::collections::HashSet;
+
+use crate::corpus::{Chapter, Corpus, Document, FrequencyTable, TfIdfTable};
+use crate::normalizer::TermForm;
+
+/// Fails if `actual` is further than `tolerance` from `expected`
+pub fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+    assert!((actual - expected).abs() < tolerance, "{} != {} (tolerance {})", actual, expected, tolerance);
+}
+
+/// The words of `text`, split on whitespace only
+pub fn words(text: &str) -> Vec<String> {
+    text.split_whitespace().map(|word| word.to_string()).collect()
+}
+
+/// A chapter with `text` as its raw text, and its whitespace-separated words
+pub fn chapter(text: &str) -> Chapter {
+    let words = words(text);
+    Chapter { text: text.to_string(), tokens: words.len(), words, ..Chapter::default() }
+}
+
+/// A UTF-8 document named (and at the path) `name`, with one chapter per text
+pub fn document(name: &str, chapters: &[&str]) -> Document {
+    Document {
+        name: name.to_string(),
+        path: name.into(),
+        encoding: "UTF-8".to_string(),
+        chapters: chapters.iter().map(|text| chapter(text)).collect(),
+        ..Document::default()
+    }
+}
+
+/// A corpus of `documents`, the words of which are already normalized
+pub fn corpus(documents: Vec<Document>) -> Corpus {
+    let vocabulary: HashSet<String> = documents.iter().flat_map(|document| &document.chapters).flat_map(|chapter| chapter.words.iter().cloned()).collect();
+    Corpus { documents, normalized_vocabulary: vocabulary.clone(), vocabulary }
+}
+
+/// Two documents (the first with two chapters) and the target phrase "red dragon", counted, with the TF-IDF of the words
+pub fn two_documents() -> (Corpus, FrequencyTable, TfIdfTable, Vec<String>) {
+    let corpus = corpus(vec![
+        document("a.txt", &["the red dragon sleeps", "red gold"]),
+        document("b.txt", &["the knight fights the dragon"]),
+    ]);
+    let phrases = vec!["red dragon".to_string()];
+    let frequencies = crate::count(&corpus, &phrases, TermForm::Normalized);
+    let tfidf = crate::tf_idf(&frequencies.without_stopwords());
+    (corpus, frequencies, tfidf, phrases)
+}
@@ -1,13 +1,15 @@
-use std::path::{Path, PathBuf};
 use std::collections::{HashMap, HashSet};
-use std::fs;
 use rayon::prelude::*;
 
 // import utils/tables.rs
 use crate::utils::tables;
 use crate::utils::utils;
+use crate::corpus::{ChapterFrequencies, Corpus, FrequencyTable, TfIdfTable};
+use crate::error::Result;
+use crate::normalizer::TermForm;
+use crate::output::{OutputPaths, OutputSettings};
 
-fn phrase_frequency(text: &str, phrases: &Vec<String>) -> HashMap<String, usize> {
+fn phrase_frequency(text: &str, phrases: &[String]) -> HashMap<String, usize> {
     let freq_map: HashMap<_, _> = phrases
         .iter()
         .map(|sub| (sub.clone(), text.match_indices(sub).count()))
@@ -16,57 +18,55 @@ fn phrase_frequency(text: &str, phrases: &Vec<String>) -> HashMap<String, usize>
     freq_map
 }
 
-// main take in data and generate frequency analysis (tables and graphs), along with a master word frequency hashmap
-// also take in phrases to search for and make a table of the frequency of those phrases
+// count take in the digested corpus and generate the master word frequency table
+// also take in phrases to search for and count the frequency of those phrases
 // then combine all the data into a single hashmap matching the file name with the word frequency hashmap
 // master word frequency hashmap: HashMap<String, Vec<HashMap<String, usize>>> is a hashmap of <file name, chapter<<word, frequency>>>
-pub fn main(folder_dir: &String, raw_data: HashMap<String, Vec<String>>, data: HashMap<String, Vec<Vec<String>>>, phrases: &Vec<String>) -> HashMap<String, Vec<HashMap<String, usize>>> {
+pub fn count(corpus: &Corpus, phrases: &[String], form: TermForm) -> FrequencyTable {
     println!("------------------------------------------------------------");
-    println!("ANALYZING WORD FREQUENCY...");
-    // start time for the whole function
-    let start_total = std::time::Instant::now();
+    println!("COUNTING WORD FREQUENCY...");
 
     let start = std::time::Instant::now();
     // Count frequency of each word in each file and chapters (in parallel) (case sensitive, but normalized capitalization)
-    let word_freq: HashMap<String, Vec<HashMap<String, usize>>> = data
+    let word_freq: ChapterFrequencies<usize> = corpus.documents
         .par_iter()
-        .map(|(file_name, chapters)| {
+        .map(|document| {
             let mut file_word_freq: Vec<HashMap<String, usize>> = Vec::new();
-            for chapter in chapters {
+            for chapter in &document.chapters {
                 let mut chapter_word_freq: HashMap<String, usize> = HashMap::new();
-                for word in chapter {
+                for word in chapter.terms(form) {
                     let count = chapter_word_freq.entry(word.clone()).or_insert(0);
                     *count += 1;
                 }
                 file_word_freq.push(chapter_word_freq);
             }
-            (file_name.clone(), file_word_freq)
+            (document.name.clone(), file_word_freq)
         })
         .collect();
     let duration = start.elapsed();
     println!("\x1b[2m  Word frequency count completed in {} ms\x1b[0m", duration.as_millis());
 
     let start = std::time::Instant::now();
-    // Count frequency of each phrase in each file and chapters using the raw_data text (in parallel) (case sensitive)
-    let phrase_freq: HashMap<String, Vec<HashMap<String, usize>>> = raw_data
+    // Count frequency of each phrase in each file and chapters using the raw chapter text (in parallel) (case sensitive)
+    let phrase_freq: ChapterFrequencies<usize> = corpus.documents
         .par_iter()
-        .map(|(file_name, chapters)| {
+        .map(|document| {
             let mut file_phrase_freq: Vec<HashMap<String, usize>> = Vec::new();
-            for chapter in chapters {
+            for chapter in &document.chapters {
                 let mut chapter_phrase_freq: HashMap<String, usize> = HashMap::new();
-                for (phrase, count) in phrase_frequency(&chapter, &phrases) {
+                for (phrase, count) in phrase_frequency(&chapter.text, phrases) {
                     chapter_phrase_freq.insert(phrase, count);
                 }
                 file_phrase_freq.push(chapter_phrase_freq);
             }
-            (file_name.clone(), file_phrase_freq)
+            (document.name.clone(), file_phrase_freq)
         })
         .collect();
     let duration = start.elapsed();
     println!("\x1b[2m  Phrase frequency count completed in {} ms\x1b[0m", duration.as_millis());
 
     let start = std::time::Instant::now();
-    let mut master_word_freq_map: HashMap<String, Vec<HashMap<String, usize>>> = HashMap::new();
+    let mut master_word_freq_map: ChapterFrequencies<usize> = HashMap::new();
     // Combine word_freq and phrase_freq into a single hashmap, merge by the appropriate chapter
     // Phrase frequency is added to the end of the word frequency vector (same chapter)
     for (file_name, word_freq_vec) in &word_freq {
@@ -76,53 +76,50 @@ pub fn main(folder_dir: &String, raw_data: HashMap<String, Vec<String>>, data: H
             for (word, freq) in word_freq {
                 combined_word_freq.insert(word.clone(), *freq);
             }
-            if phrase_freq.contains_key(file_name) {
-                let phrase_freq_vec = phrase_freq.get(file_name).unwrap();
-                if phrase_freq_vec.len() > i {
-                    let phrase_freq = phrase_freq_vec.get(i).unwrap();
-                    for (phrase, freq) in phrase_freq {
-                        combined_word_freq.insert(phrase.clone(), *freq);
-                    }
+            if let Some(phrase_freq) = phrase_freq.get(file_name).and_then(|phrase_freq_vec| phrase_freq_vec.get(i)) {
+                for (phrase, freq) in phrase_freq {
+                    combined_word_freq.insert(phrase.clone(), *freq);
                 }
             }
             combined_word_freq_vec.push(combined_word_freq);
         }
         master_word_freq_map.insert(file_name.clone(), combined_word_freq_vec);
     }
-
-    let mut simple_word_freq_map: HashMap<String, HashMap<String, usize>> = HashMap::new();
-    for (file_name, word_freq_vec) in &master_word_freq_map {
-        let mut simple_word_freq: HashMap<String, usize> = HashMap::new();
-        for word_freq in word_freq_vec {
-            for (word, freq) in word_freq {
-                let count = simple_word_freq.entry(word.clone()).or_insert(0);
-                *count += *freq;
-            }
-        }
-        simple_word_freq_map.insert(file_name.clone(), simple_word_freq);
-    }
     let duration = start.elapsed();
     println!("\x1b[2m  Frequency maps generated in {} ms\x1b[0m", duration.as_millis());
 
+    FrequencyTable { files: master_word_freq_map }
+}
+
+// main take in the master word frequency table and generate frequency analysis (tables and graphs)
+// the wide tables are only written if the layout includes them, see long_table for the other one
+pub fn main(paths: &OutputPaths, frequencies: &FrequencyTable, phrases: &[String], output: &OutputSettings) -> Result<()> {
+    println!("------------------------------------------------------------");
+    println!("ANALYZING WORD FREQUENCY...");
+    // start time for the whole function
+    let start_total = std::time::Instant::now();
+
+    let start = std::time::Instant::now();
+    let simple_word_freq_map = frequencies.file_totals();
+    let duration = start.elapsed();
+    println!("\x1b[2m  File totals generated in {} ms\x1b[0m", duration.as_millis());
+
     let simple_word_freq_map_no_stopwords = utils::remove_stopwords_no_chapters(&simple_word_freq_map);
 
     let start = std::time::Instant::now();
     // get all the file names
-    let mut file_names: Vec<String> = Vec::new();
-    for (file_name, _) in &simple_word_freq_map {
-        file_names.push(file_name.clone());
-    }
+    let mut file_names: Vec<String> = simple_word_freq_map.keys().cloned().collect();
     file_names.sort();
     // get all the words into a HashSet
     let mut words_complete: HashSet<String> = HashSet::new();
-    for (_, word_freq) in &simple_word_freq_map {
+    for word_freq in simple_word_freq_map.values() {
         for word in word_freq.keys() {
             words_complete.insert(word.clone());
         }
     }
     // same as words_complete, but without stopwords
     let mut words_no_stopword: HashSet<String> = HashSet::new();
-    for (_, word_freq) in &simple_word_freq_map_no_stopwords {
+    for word_freq in simple_word_freq_map_no_stopwords.values() {
         for word in word_freq.keys() {
             words_no_stopword.insert(word.clone());
         }
@@ -133,20 +130,17 @@ pub fn main(folder_dir: &String, raw_data: HashMap<String, Vec<String>>, data: H
 
     // Generate CSV table(s)
     let start = std::time::Instant::now();
-    let folder_dir_path = PathBuf::from(folder_dir.clone());
-    let folder_name = Path::new(folder_dir).file_name().unwrap().to_str().unwrap();
-    let outputs_folder_path = folder_dir_path.join("outputs");
-    if !outputs_folder_path.exists() {
-        fs::create_dir(&outputs_folder_path).expect("Failed to create outputs folder");
-    }
+    utils::create_output_folder(&paths.directory)?;
 
-    let csv_string = tables::combined_file_map_to_csv_string_usize(&file_names, &words_complete, &simple_word_freq_map, &phrases);
-    let output_file_path = outputs_folder_path.join(format!("{}_wordFreq.csv", folder_name));
-    fs::write(&output_file_path, csv_string.as_bytes()).expect("Unable to write file");
+    if output.wide_csv() {
+        let csv_string = tables::combined_file_map_to_csv_string_usize(&file_names, &words_complete, &simple_word_freq_map, phrases, output)?;
+        let output_file_path = paths.file("wordFreq", "csv");
+        utils::write_output_file(&output_file_path, csv_string.as_bytes())?;
 
-    let csv_string = tables::combined_file_map_to_csv_string_usize(&file_names, &words_no_stopword, &simple_word_freq_map_no_stopwords, &phrases);
-    let output_file_path = outputs_folder_path.join(format!("{}_wordFreq_no-stopwords.csv", folder_name));
-    fs::write(&output_file_path, csv_string.as_bytes()).expect("Unable to write file");
+        let csv_string = tables::combined_file_map_to_csv_string_usize(&file_names, &words_no_stopword, &simple_word_freq_map_no_stopwords, phrases, output)?;
+        let output_file_path = paths.file("wordFreq_no-stopwords", "csv");
+        utils::write_output_file(&output_file_path, csv_string.as_bytes())?;
+    }
 
     // More CSV here
 
@@ -161,24 +155,22 @@ pub fn main(folder_dir: &String, raw_data: HashMap<String, Vec<String>>, data: H
     let duration = start_total.elapsed();
     println!("Word frequency analysis completed in {} ms", duration.as_millis());
 
-    // drop all intermediate data
-    drop(file_names);
-    drop(words_complete);
-    drop(words_no_stopword);
-    drop(output_file_path);
-    drop(outputs_folder_path);
-    drop(folder_name);
-    drop(folder_dir_path);
-    drop(start);
-    drop(duration);
-    drop(start_total);
-    drop(duration);
-    drop(simple_word_freq_map_no_stopwords);
-    drop(simple_word_freq_map);
-    drop(phrase_freq);
-    drop(word_freq);
-    drop(csv_string);
-
-
-    master_word_freq_map
-}
\ No newline at end of file
+    Ok(())
+}
+
+// long_table write the word and phrase counts of every chapter, one row per term, with the TF-IDF from tf_idf::main (if any)
+pub fn long_table(paths: &OutputPaths, frequencies: &FrequencyTable, tfidf: &TfIdfTable, phrases: &[String], output: &OutputSettings) -> Result<()> {
+    println!("------------------------------------------------------------");
+    println!("WRITING LONG TABLE...");
+    let start_total = std::time::Instant::now();
+
+    utils::create_output_folder(&paths.directory)?;
+    let csv_string = tables::long_table_to_csv_string(frequencies, tfidf, phrases, output)?;
+    let output_file_path = paths.file("long", "csv");
+    utils::write_output_file(&output_file_path, csv_string.as_bytes())?;
+
+    let duration = start_total.elapsed();
+    println!("Long table completed in {} ms", duration.as_millis());
+
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use std::path::Path;
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{graph, utils, wordcloud};
+use crate::corpus::{FrequencyTable, TfIdfTable};
+use crate::error::{Error, Result};
+use crate::output::{self, OutputPaths};
+use crate::settings::Settings;
+
+// largest width and height of a word cloud, in pixels
+const MAX_SIZE: u32 = 16_384;
+
+/// What sets the size of the words
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WordCloudWeight {
+    /// Word frequency, stopwords removed
+    Frequency,
+    /// TF-IDF summed over the chapters (stopwords removed)
+    TfIdf,
+}
+
+/// How the weights are turned into font sizes, between the minimum and maximum font size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FontScaling {
+    Linear,
+    /// Square root of the weight: fewer tiny words
+    Sqrt,
+    /// Logarithm of the weight: the most even sizes
+    Log,
+}
+
+/// The "wordcloud" section of settings.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WordCloudSettings {
+    pub weight: WordCloudWeight,
+    pub max_words: usize,
+    pub width: u32,
+    pub height: u32,
+    pub min_font_size: f32,
+    pub max_font_size: f32,
+    pub scaling: FontScaling,
+    /// "#rrggbb" colors, given to the words in turn from the largest one
+    pub palette: Vec<String>,
+    pub background: String,
+    /// TrueType/OpenType font used to measure and draw the words. If empty, a common system font is used (DejaVu Sans or Arial)
+    pub font_file: String,
+}
+
+impl Default for WordCloudSettings {
+    fn default() -> Self {
+        WordCloudSettings {
+            weight: WordCloudWeight::Frequency,
+            max_words: 100,
+            width: 800,
+            height: 500,
+            min_font_size: 10.0,
+            max_font_size: 80.0,
+            scaling: FontScaling::Sqrt,
+            palette: graph::PALETTE.iter().map(|color| color.to_string()).collect(),
+            background: "#ffffff".to_string(),
+            font_file: String::new(),
+        }
+    }
+}
+
+impl WordCloudSettings {
+    // check the values that serde cannot check by itself
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.palette.is_empty() {
+            return Err("the word cloud palette needs at least one color".to_string());
+        }
+        for color in self.palette.iter().chain([&self.background]) {
+            wordcloud::parse_color(color)?;
+        }
+        if !(1..=MAX_SIZE).contains(&self.width) || !(1..=MAX_SIZE).contains(&self.height) {
+            return Err(format!("the word cloud width and height must be between 1 and {} pixels", MAX_SIZE));
+        }
+        if !(self.min_font_size > 0.0 && self.min_font_size <= self.max_font_size) {
+            return Err(format!("invalid word cloud font sizes {}..{}", self.min_font_size, self.max_font_size));
+        }
+        if !self.font_file.is_empty() && !Path::new(&self.font_file).is_file() {
+            return Err(format!("font file \"{}\" does not exist", self.font_file));
+        }
+        Ok(())
+    }
+}
+
+/// The `max_words` heaviest words with their font size, largest first
+pub fn font_sizes(weights: &HashMap<String, f64>, settings: &WordCloudSettings) -> Vec<(String, f32)> {
+    let mut words: Vec<(&String, f64)> = weights.iter().filter(|(_, weight)| **weight > 0.0).map(|(word, weight)| (word, *weight)).collect();
+    words.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then_with(|| a.0.cmp(b.0)));
+    words.truncate(settings.max_words);
+
+    let scale = |weight: f64| match settings.scaling {
+        FontScaling::Linear => weight,
+        FontScaling::Sqrt => weight.sqrt(),
+        FontScaling::Log => (1.0 + weight).ln(),
+    };
+    let (max, min) = match (words.first(), words.last()) {
+        (Some(first), Some(last)) => (scale(first.1), scale(last.1)),
+        _ => return Vec::new(),
+    };
+    words
+        .into_iter()
+        .map(|(word, weight)| {
+            let t = if max > min { (scale(weight) - min) / (max - min) } else { 1.0 };
+            (word.clone(), settings.min_font_size + (settings.max_font_size - settings.min_font_size) * t as f32)
+        })
+        .collect()
+}
+
+// <file name, <word, weight>>, plus the corpus-wide weights
+// tfidf: TF-IDF without stopwords, empty if there is a single document
+fn weights(frequencies: &FrequencyTable, tfidf: &TfIdfTable, settings: &WordCloudSettings) -> (HashMap<String, HashMap<String, f64>>, HashMap<String, f64>) {
+    let without_stopwords = frequencies.without_stopwords();
+    let to_f64 = |map: HashMap<String, usize>| map.into_iter().map(|(word, count)| (word, count as f64)).collect::<HashMap<String, f64>>();
+    match settings.weight {
+        WordCloudWeight::TfIdf if !tfidf.is_empty() => {
+            let mut corpus: HashMap<String, f64> = HashMap::new();
+            let files = tfidf.files
+                .iter()
+                .map(|(file_name, chapters)| {
+                    let mut file: HashMap<String, f64> = HashMap::new();
+                    for (word, value) in chapters.iter().flatten() {
+                        *file.entry(word.clone()).or_insert(0.0) += value;
+                        *corpus.entry(word.clone()).or_insert(0.0) += value;
+                    }
+                    (file_name.clone(), file)
+                })
+                .collect();
+            (files, corpus)
+        }
+        _ => {
+            if settings.weight == WordCloudWeight::TfIdf {
+                println!("\x1b[33m  WARNING: TF-IDF needs more than one document, the word clouds use the word frequencies instead\x1b[0m");
+            }
+            let files = without_stopwords.file_totals().into_iter().map(|(file_name, map)| (file_name, to_f64(map))).collect();
+            (files, to_f64(without_stopwords.corpus_totals()))
+        }
+    }
+}
+
+// main draw one word cloud per file and one for the corpus, as SVG and PNG
+pub fn main(paths: &OutputPaths, frequencies: &FrequencyTable, tfidf: &TfIdfTable, settings: &Settings) -> Result<()> {
+    println!("------------------------------------------------------------");
+    println!("DRAWING WORD CLOUDS...");
+    // start time for the whole function
+    let start_total = std::time::Instant::now();
+
+    let cloud_settings = &settings.wordcloud;
+    let font = wordcloud::load_font(&cloud_settings.font_file)?;
+
+    utils::create_output_folder(&paths.directory)?;
+    let (files, corpus) = weights(frequencies, tfidf, cloud_settings);
+    let mut file_names: Vec<&String> = files.keys().collect();
+    file_names.sort();
+    let clouds = file_names
+        .into_iter()
+        .map(|file_name| (format!("wordcloud_{}", output::file_name_part(file_name)), &files[file_name]))
+        .chain([("wordcloud".to_string(), &corpus)]);
+
+    for (name, weights) in clouds {
+        let start = std::time::Instant::now();
+        let words = font_sizes(weights, cloud_settings);
+        let (width, height) = (cloud_settings.width as f32, cloud_settings.height as f32);
+        let placed = wordcloud::layout(&words, width, height, cloud_settings.min_font_size, &cloud_settings.palette, Some(&font));
+
+        let svg = wordcloud::to_svg(&placed, width, height, &cloud_settings.background);
+        utils::write_output_file(&paths.file(&name, "svg"), svg.as_bytes())?;
+        let output_file_path = paths.file(&name, "png");
+        let png = wordcloud::to_png(&placed, cloud_settings.width, cloud_settings.height, &cloud_settings.background, &font)
+            .map_err(|error| Error::Output { path: output_file_path.clone(), source: std::io::Error::other(error) })?;
+        utils::write_output_file(&output_file_path, &png)?;
+        let duration = start.elapsed();
+        println!("\x1b[2m  {} ({} of {} words placed) in {} ms\x1b[0m", name, placed.len(), words.len(), duration.as_millis());
+    }
+
+    // end time
+    let duration = start_total.elapsed();
+    println!("Word clouds completed in {} ms", duration.as_millis());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn largest_weight_at_the_maximum_size() {
+        let weights = HashMap::from([("big".to_string(), 100.0), ("mid".to_string(), 50.5), ("small".to_string(), 1.0), ("none".to_string(), 0.0)]);
+        let settings = WordCloudSettings { scaling: FontScaling::Linear, ..WordCloudSettings::default() };
+        let sizes = font_sizes(&weights, &settings);
+        assert_eq!(sizes, [("big".to_string(), 80.0), ("mid".to_string(), 45.0), ("small".to_string(), 10.0)]);
+
+        let sqrt = font_sizes(&weights, &WordCloudSettings { max_words: 2, ..WordCloudSettings::default() });
+        assert_eq!(sqrt.iter().map(|(word, _)| word.as_str()).collect::<Vec<&str>>(), ["big", "mid"]);
+        assert_eq!((sqrt[0].1, sqrt[1].1), (80.0, 10.0));
+    }
+
+    #[test]
+    fn invalid_palettes() {
+        let palette = |colors: &[&str]| WordCloudSettings { palette: colors.iter().map(|color| color.to_string()).collect(), ..WordCloudSettings::default() };
+        assert!(palette(&["#ff0000", "#00ff00"]).validate().is_ok());
+        assert!(palette(&[]).validate().is_err());
+        assert!(palette(&["#ff0000", "#abc"]).validate().is_err());
+        assert!(palette(&["red"]).validate().is_err());
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::{HashMap, HashSet};
+use rayon::prelude::*;
+
+use crate::utils::{tables, utils};
+use crate::corpus::Corpus;
+use crate::error::Result;
+use crate::normalizer::TermForm;
+use crate::output::{OutputPaths, OutputSettings};
+
+// number of (tokens, types) points sampled along the token stream for the Heaps fit
+const HEAPS_POINTS: usize = 200;
+
+/// Zipf and Heaps law fits of a file or of the whole corpus. Parameters that cannot be fitted (too few words) are NaN
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZipfFit {
+    /// File name, or "Corpus Total"
+    pub scope: String,
+    pub tokens: usize,
+    pub types: usize,
+    /// Zipf exponent s of frequency ∝ rank^-s, maximum likelihood estimate
+    pub zipf_mle: f64,
+    /// Zipf exponent from the least squares fit of log(frequency) on log(rank)
+    pub zipf_regression: f64,
+    pub zipf_r2: f64,
+    /// Heaps' law: types = K * tokens^β, fitted by least squares on the log-log vocabulary growth curve
+    pub heaps_k: f64,
+    pub heaps_beta: f64,
+    pub heaps_r2: f64,
+    /// (word, frequency), most frequent first: the word at index i has rank i + 1
+    pub ranks: Vec<(String, usize)>,
+}
+
+/// Least squares fit of y = slope * x + intercept: (slope, intercept, R²)
+pub fn linear_regression(points: &[(f64, f64)]) -> (f64, f64, f64) {
+    let n = points.len() as f64;
+    if points.len() < 2 {
+        return (f64::NAN, f64::NAN, f64::NAN);
+    }
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let syy: f64 = points.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+    if sxx == 0.0 {
+        return (f64::NAN, f64::NAN, f64::NAN);
+    }
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    // a flat line explains everything when y does not vary at all
+    let r2 = if syy == 0.0 { 1.0 } else { sxy * sxy / (sxx * syy) };
+    (slope, intercept, r2)
+}
+
+// log-likelihood of the ranked frequencies under a Zipf distribution of exponent s over ranks 1..=V
+fn zipf_log_likelihood(s: f64, frequencies: &[usize], sum_f_ln_r: f64, tokens: f64) -> f64 {
+    let normalization: f64 = (1..=frequencies.len()).map(|r| (r as f64).powf(-s)).sum();
+    -s * sum_f_ln_r - tokens * normalization.ln()
+}
+
+// the log-likelihood is concave in s, so a golden-section search finds its maximum
+fn zipf_mle(frequencies: &[usize]) -> f64 {
+    if frequencies.len() < 2 {
+        return f64::NAN;
+    }
+    let tokens = frequencies.iter().sum::<usize>() as f64;
+    let sum_f_ln_r: f64 = frequencies.iter().enumerate().map(|(i, f)| *f as f64 * ((i + 1) as f64).ln()).sum();
+    let likelihood = |s: f64| zipf_log_likelihood(s, frequencies, sum_f_ln_r, tokens);
+
+    let ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+    let (mut low, mut high) = (0.0, 10.0);
+    let mut a = high - ratio * (high - low);
+    let mut b = low + ratio * (high - low);
+    let (mut likelihood_a, mut likelihood_b) = (likelihood(a), likelihood(b));
+    while high - low > 1e-6 {
+        if likelihood_a > likelihood_b {
+            high = b;
+            (b, likelihood_b) = (a, likelihood_a);
+            a = high - ratio * (high - low);
+            likelihood_a = likelihood(a);
+        } else {
+            low = a;
+            (a, likelihood_a) = (b, likelihood_b);
+            b = low + ratio * (high - low);
+            likelihood_b = likelihood(b);
+        }
+    }
+    (low + high) / 2.0
+}
+
+fn fit(scope: &str, tokens: &[&String]) -> ZipfFit {
+    let mut counts: HashMap<&String, usize> = HashMap::new();
+    for token in tokens {
+        *counts.entry(*token).or_insert(0) += 1;
+    }
+    let mut ranks: Vec<(String, usize)> = counts.iter().map(|(word, count)| (word.to_string(), *count)).collect();
+    ranks.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let frequencies: Vec<usize> = ranks.iter().map(|(_, count)| *count).collect();
+    let zipf_points: Vec<(f64, f64)> = frequencies.iter().enumerate().map(|(i, f)| (((i + 1) as f64).ln(), (*f as f64).ln())).collect();
+    let (slope, _, zipf_r2) = linear_regression(&zipf_points);
+
+    // vocabulary growth, sampled at (roughly) log-spaced positions of the token stream
+    let mut checkpoints: HashSet<usize> = (0..=HEAPS_POINTS)
+        .map(|i| (tokens.len() as f64).powf(i as f64 / HEAPS_POINTS as f64).round() as usize)
+        .collect();
+    checkpoints.insert(tokens.len());
+    let mut heaps_points: Vec<(f64, f64)> = Vec::new();
+    let mut seen: HashSet<&String> = HashSet::new();
+    for (i, token) in tokens.iter().enumerate() {
+        seen.insert(token);
+        if checkpoints.contains(&(i + 1)) {
+            heaps_points.push((((i + 1) as f64).ln(), (seen.len() as f64).ln()));
+        }
+    }
+    let (heaps_beta, heaps_intercept, heaps_r2) = linear_regression(&heaps_points);
+
+    ZipfFit {
+        scope: scope.to_string(),
+        tokens: tokens.len(),
+        types: ranks.len(),
+        zipf_mle: zipf_mle(&frequencies),
+        zipf_regression: -slope,
+        zipf_r2,
+        heaps_k: heaps_intercept.exp(),
+        heaps_beta,
+        heaps_r2,
+        ranks,
+    }
+}
+
+/// Fit Zipf's and Heaps' laws on the words of each file (sorted by name), then on the whole corpus (last)
+pub fn calculate_zipf(corpus: &Corpus, form: TermForm) -> Vec<ZipfFit> {
+    let start = std::time::Instant::now();
+    let mut documents: Vec<_> = corpus.documents.iter().collect();
+    documents.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut result: Vec<ZipfFit> = documents
+        .par_iter()
+        .map(|document| {
+            let tokens: Vec<&String> = document.chapters.iter().flat_map(|chapter| chapter.terms(form)).collect();
+            fit(&document.name, &tokens)
+        })
+        .collect();
+    let tokens: Vec<&String> = documents.iter().flat_map(|document| &document.chapters).flat_map(|chapter| chapter.terms(form)).collect();
+    result.push(fit("Corpus Total", &tokens));
+
+    let duration = start.elapsed();
+    println!("\x1b[2m  Zipf and Heaps laws fitted in {} ms\x1b[0m", duration.as_millis());
+
+    result
+}
+
+// main fit Zipf's and Heaps' laws, and write the parameters and the rank/frequency points
+pub fn main(paths: &OutputPaths, corpus: &Corpus, form: TermForm, output: &OutputSettings) -> Result<Vec<ZipfFit>> {
+    println!("------------------------------------------------------------");
+    println!("FITTING ZIPF'S AND HEAPS' LAWS...");
+    // start time for the whole function
+    let start_total = std::time::Instant::now();
+
+    let fits = calculate_zipf(corpus, form);
+    if let Some(corpus_fit) = fits.last() {
+        println!("Corpus: Zipf exponent {:.3} (MLE), {:.3} (R² {:.3}), Heaps β {:.3} (R² {:.3})", corpus_fit.zipf_mle, corpus_fit.zipf_regression, corpus_fit.zipf_r2, corpus_fit.heaps_beta, corpus_fit.heaps_r2);
+    }
+
+    utils::create_output_folder(&paths.directory)?;
+    let csv_string = tables::zipf_to_csv_string(&fits, output)?;
+    let output_file_path = paths.file("zipf", "csv");
+    utils::write_output_file(&output_file_path, csv_string.as_bytes())?;
+
+    let csv_string = tables::zipf_points_to_csv_string(&fits, output)?;
+    let output_file_path = paths.file("zipf_points", "csv");
+    utils::write_output_file(&output_file_path, csv_string.as_bytes())?;
+
+    // end time
+    let duration = start_total.elapsed();
+    println!("Zipf and Heaps analysis completed in {} ms", duration.as_millis());
+
+    Ok(fits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyses::test_util::{assert_close, words};
+
+    #[test]
+    fn mle_of_exact_zipf_frequencies() {
+        // frequencies exactly proportional to rank^-s: the likelihood equation Σ f ln r / N = E[ln r] holds at s.
+        // The bisection stops within 1e-6 of the root, hence the looser tolerance
+        assert_close(zipf_mle(&[60, 30, 20, 15, 12, 10]), 1.0, 1e-5);
+        assert_close(zipf_mle(&[144, 36, 16, 9]), 2.0, 1e-5);
+        // all ranks equally frequent
+        assert_close(zipf_mle(&[5, 5, 5]), 0.0, 1e-5);
+        assert!(zipf_mle(&[7]).is_nan());
+    }
+
+    #[test]
+    fn regression() {
+        let points: Vec<(f64, f64)> = [60.0_f64, 30.0, 20.0, 15.0].iter().enumerate().map(|(i, f)| (((i + 1) as f64).ln(), f.ln())).collect();
+        let (slope, intercept, r2) = linear_regression(&points);
+        assert_close(slope, -1.0, 1e-6);
+        assert_close(intercept, 60f64.ln(), 1e-6);
+        assert_close(r2, 1.0, 1e-6);
+        assert!(linear_regression(&[(1.0, 2.0)]).0.is_nan());
+    }
+
+    #[test]
+    fn fit_ranks() {
+        let words = words("b a b c b a");
+        let fit = fit("a.txt", &words.iter().collect::<Vec<&String>>());
+        assert_eq!((fit.tokens, fit.types), (6, 3));
+        assert_eq!(fit.ranks, [("b".to_string(), 3), ("a".to_string(), 2), ("c".to_string(), 1)]);
+    }
+}
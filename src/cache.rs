@@ -0,0 +1,350 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::corpus::{Chapter, ChapterFrequencies, Corpus, Document, FrequencyTable};
+use crate::error::{Error, Result};
+use crate::normalizer::{Normalizer, TermForm};
+use crate::settings::Settings;
+use crate::tokenizer::{self, Tokenizer};
+use crate::utils::{encoding, utils};
+
+/// Analyses that only need the word counts and the number of words of each chapter, so they can run on cached files
+pub const CACHED_ANALYSES: [&str; 5] = ["word_frequency", "tf_idf", "dispersion", "keyness", "wordcloud"];
+
+/// The "cache" section of settings.json (disabled by default)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheSettings {
+    /// Keep the counts of each file on disk, and reuse them while the file and the settings it was counted with do not change
+    pub enabled: bool,
+    /// Folder of the cache, or empty for a `cache` folder in the output folder
+    pub directory: String,
+}
+
+impl CacheSettings {
+    /// Folder of the cache, `output_dir` is the output folder of the run (without the run subfolder)
+    pub fn folder(&self, output_dir: &Path) -> PathBuf {
+        match self.directory.is_empty() {
+            true => output_dir.join("cache"),
+            false => PathBuf::from(&self.directory),
+        }
+    }
+}
+
+/// The selected analyses that need the text of the files, and so cannot run on cached files
+pub fn uncached_analyses(settings: &Settings) -> Vec<&str> {
+    settings.analyses.iter().map(String::as_str).filter(|name| !CACHED_ANALYSES.contains(name)).collect()
+}
+
+/// Whether the cache is enabled and all the selected analyses can run without the text of the files
+pub fn usable(settings: &Settings) -> bool {
+    settings.cache.enabled && uncached_analyses(settings).is_empty()
+}
+
+/// Counts of one chapter, before the capitalization is normalized (that depends on the whole corpus)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CachedChapter {
+    tokens: usize,
+    words: HashMap<String, usize>,
+    phrases: HashMap<String, usize>,
+}
+
+/// One cache file: everything that is needed of an input file once it has been read, split and counted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedFile {
+    encoding: String,
+    chapters: Vec<CachedChapter>,
+}
+
+// everything the counts of a file depend on: its content, and the settings used to read, split, tokenize and normalize it
+fn cache_key(file_hash: &str, settings: &Settings) -> String {
+    let key = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "file": file_hash,
+        "encoding": settings.encoding,
+        "chapter_separator": settings.chapter_separator,
+        "phrases": settings.phrases,
+        "tokenizer": settings.tokenizer,
+        "normalization": settings.normalization,
+    });
+    utils::sha256_hex(key.to_string().as_bytes())
+}
+
+// decode, split into chapters, tokenize and count a file that is not in the cache
+fn count_file(file: &Path, bytes: &[u8], settings: &Settings, tokenizer: &dyn Tokenizer) -> Result<CachedFile> {
+    let forced_encoding = encoding::encoding_from_label(&settings.encoding)
+        .map_err(|reason| Error::Settings { path: PathBuf::new(), reason })?;
+    let (text, encoding) = encoding::decode(bytes, forced_encoding).map_err(|reason| Error::Encoding { path: file.to_path_buf(), reason })?;
+
+    let chapters = utils::split_into_chapters(&text, &settings.chapter_separator)
+        .iter()
+        .map(|chapter| {
+            let words = tokenizer.tokenize(chapter);
+            let mut word_counts: HashMap<String, usize> = HashMap::new();
+            for word in &words {
+                *word_counts.entry(word.clone()).or_insert(0) += 1;
+            }
+            let phrases = settings.phrases.iter().map(|phrase| (phrase.clone(), chapter.match_indices(phrase.as_str()).count())).collect();
+            CachedChapter { tokens: words.len(), words: word_counts, phrases }
+        })
+        .collect();
+
+    Ok(CachedFile { encoding: encoding.to_string(), chapters })
+}
+
+// an input file as loaded from the cache or counted: its counts, whether they were cached, and the size and hash of the bytes read
+struct LoadedFile {
+    counts: CachedFile,
+    hit: bool,
+    cache_name: String,
+    size: u64,
+    sha256: String,
+}
+
+// first 16 hex digits of the SHA-256 of a path or a document name, to tell in a cache file name which input it belongs to
+fn short_hash(text: &str) -> String {
+    utils::sha256_hex(text.as_bytes())[..16].to_string()
+}
+
+// load the counts of a file from the cache, or count it and add it to the cache.
+// Cache files are named "<prefix>-<cache key>.json", where the prefix identifies the input file (see `digest_and_count`)
+fn load_or_count(file: &Path, prefix: &str, cache_folder: &Path, settings: &Settings, tokenizer: &dyn Tokenizer) -> Result<LoadedFile> {
+    let bytes = fs::read(file).map_err(|source| Error::Io { path: file.to_path_buf(), source })?;
+    let (size, sha256) = (bytes.len() as u64, utils::sha256_hex(&bytes));
+    let cache_name = format!("{}-{}.json", prefix, cache_key(&sha256, settings));
+    let cache_file = cache_folder.join(&cache_name);
+
+    // a cache file that cannot be read (e.g. from an interrupted run) is counted again
+    if let Some(cached) = fs::read(&cache_file).ok().and_then(|cached| serde_json::from_slice::<CachedFile>(&cached).ok()) {
+        return Ok(LoadedFile { counts: cached, hit: true, cache_name, size, sha256 });
+    }
+
+    let counted = count_file(file, &bytes, settings, tokenizer)?;
+    let written = serde_json::to_vec(&counted).map_err(std::io::Error::other).and_then(|json| fs::write(&cache_file, json));
+    if let Err(error) = written {
+        println!("\x1b[33m  WARNING: Could not write the cache file {}: {}\x1b[0m", cache_file.display(), error);
+    }
+    Ok(LoadedFile { counts: counted, hit: false, cache_name, size, sha256 })
+}
+
+// remove the cache files of the input folder (named "<folder_prefix>-...") that this run did not use: those of files that changed,
+// were read with other settings, or are no longer analyzed. `current` maps the prefix of each input file to the name of its cache file,
+// or to None if the file could not be read (its cache files are kept). Returns the number of files removed
+fn prune(cache_folder: &Path, folder_prefix: &str, current: &HashMap<String, Option<String>>) -> usize {
+    let entries = match fs::read_dir(cache_folder) {
+        Ok(entries) => entries,
+        Err(error) => {
+            println!("\x1b[33m  WARNING: Could not list the cache folder {}: {}\x1b[0m", cache_folder.display(), error);
+            return 0;
+        }
+    };
+
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with(folder_prefix) || !name.ends_with(".json") {
+            continue;
+        }
+        // "<folder_prefix>-<file prefix>-<cache key>.json"
+        let stale = match name.get(..folder_prefix.len() + 17).and_then(|prefix| current.get(prefix)) {
+            Some(Some(used)) => *used != name,
+            Some(None) => false,
+            None => true,
+        };
+        if !stale {
+            continue;
+        }
+        match fs::remove_file(entry.path()) {
+            Ok(()) => removed += 1,
+            Err(error) => println!("\x1b[33m  WARNING: Could not remove the cache file {}: {}\x1b[0m", entry.path().display(), error),
+        }
+    }
+    removed
+}
+
+/// Same as digest then count, but the counts of each file are read from the cache in `cache_folder` if the file and the settings have not changed.
+/// Only the capitalization normalization, stemming or lemmatization, and the merge into the frequency table are done for the whole corpus.
+/// The chapters of the returned corpus have their number of words, but neither their text nor their words
+pub fn digest_and_count(root: &Path, file_list: &[PathBuf], settings: &Settings, cache_folder: &Path) -> Result<(Corpus, FrequencyTable)> {
+    println!("------------------------------------------------------------");
+    println!("COUNTING WORD FREQUENCY (CACHED)...");
+    let start = std::time::Instant::now();
+
+    fs::create_dir_all(cache_folder).map_err(|source| Error::Output { path: cache_folder.to_path_buf(), source })?;
+    let tokenizer = tokenizer::from_settings(&settings.tokenizer);
+    // the cache files of an input file start with the hash of the input folder and of the file name, so that the stale ones can be found
+    let folder_prefix = short_hash(&fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf()).to_string_lossy());
+    let names = utils::document_names(root, file_list);
+    let prefixes: Vec<String> = names.iter().map(|name| format!("{}-{}", folder_prefix, short_hash(name))).collect();
+    // read the files in parallel, the cache files are independent of each other
+    let loaded: Vec<Result<LoadedFile>> = file_list
+        .par_iter()
+        .zip(&prefixes)
+        .map(|(file, prefix)| load_or_count(file, prefix, cache_folder, settings, tokenizer.as_ref()))
+        .collect();
+
+    // files that cannot be read are skipped with a warning, like in digest
+    let mut files: Vec<(String, PathBuf, LoadedFile)> = Vec::new();
+    let mut errors: Vec<Error> = Vec::new();
+    let mut current: HashMap<String, Option<String>> = HashMap::new();
+    let mut hits = 0;
+    for (((file, name), prefix), result) in file_list.iter().zip(names).zip(prefixes).zip(loaded) {
+        match result {
+            Ok(loaded) => {
+                hits += loaded.hit as usize;
+                current.insert(prefix, Some(loaded.cache_name.clone()));
+                files.push((name, file.clone(), loaded));
+            }
+            Err(error) => {
+                println!("\x1b[33m  WARNING: Skipping file. {}\x1b[0m", error);
+                current.insert(prefix, None);
+                errors.push(error);
+            }
+        }
+    }
+    let removed = prune(cache_folder, &folder_prefix, &current);
+    if files.is_empty() {
+        return Err(match errors.is_empty() {
+            true => Error::NoFiles { path: root.to_path_buf(), include: settings.include.clone() },
+            false => errors.remove(0),
+        });
+    }
+    let duration = start.elapsed();
+    println!("\x1b[2m  {} files loaded from the cache, {} counted ({} stale cache files removed) in {} ms\x1b[0m", hits, files.len() - hits, removed, duration.as_millis());
+
+    // corpus-level steps: a word is written in lowercase if it appears in lowercase anywhere in the corpus, then stemmed or lemmatized
+    let start = std::time::Instant::now();
+    let vocabulary: HashSet<String> = files
+        .iter()
+        .flat_map(|(_, _, loaded)| &loaded.counts.chapters)
+        .flat_map(|chapter| chapter.words.keys().cloned())
+        .collect();
+    let capitalization: HashMap<&String, String> = vocabulary
+        .iter()
+        .map(|word| {
+            let lowercase = word.to_lowercase();
+            (word, if vocabulary.contains(&lowercase) { lowercase } else { word.clone() })
+        })
+        .collect();
+    let normalized_vocabulary: HashSet<String> = capitalization.values().cloned().collect();
+    let normalizer = Normalizer::from_settings(&settings.normalization)?.filter(|_| settings.normalization.report == TermForm::Normalized);
+    let terms: HashMap<&String, String> = match &normalizer {
+        Some(normalizer) => normalized_vocabulary.par_iter().map(|word| (word, normalizer.normalize(word, &normalized_vocabulary))).collect(),
+        None => normalized_vocabulary.iter().map(|word| (word, word.clone())).collect(),
+    };
+
+    let mut documents: Vec<Document> = Vec::new();
+    let mut table: ChapterFrequencies<usize> = HashMap::new();
+    for (name, path, loaded) in files {
+        let cached = loaded.counts;
+        let mut chapters: Vec<HashMap<String, usize>> = Vec::new();
+        for chapter in &cached.chapters {
+            let mut counts: HashMap<String, usize> = HashMap::new();
+            for (word, count) in &chapter.words {
+                *counts.entry(terms[&capitalization[word]].clone()).or_insert(0) += count;
+            }
+            // target phrases replace a word of the same name, as in word_frequency::count
+            counts.extend(chapter.phrases.clone());
+            chapters.push(counts);
+        }
+        let document_chapters = cached.chapters.iter().map(|chapter| Chapter { tokens: chapter.tokens, ..Chapter::default() }).collect();
+        documents.push(Document {
+            name: name.clone(),
+            path,
+            encoding: cached.encoding,
+            size: loaded.size,
+            sha256: loaded.sha256,
+            chapters: document_chapters,
+        });
+        table.insert(name, chapters);
+    }
+    let duration = start.elapsed();
+    println!("\x1b[2m  Corpus of {} unique words normalized and merged in {} ms\x1b[0m", normalized_vocabulary.len(), duration.as_millis());
+
+    Ok((Corpus { documents, vocabulary, normalized_vocabulary }, FrequencyTable { files: table }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::NormalizationMode;
+
+    // an empty folder of its own in the temporary folder
+    fn folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("cache_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    fn cache_files(cache_folder: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(cache_folder).unwrap().map(|entry| entry.unwrap().file_name().to_string_lossy().to_string()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn changed_file_or_normalization_misses_the_cache() {
+        let root = folder("round_trip");
+        let cache_folder = root.join("cache");
+        fs::create_dir_all(&cache_folder).unwrap();
+        let file = root.join("a.txt");
+        fs::write(&file, "The cat sat on the mat").unwrap();
+        let mut settings = Settings::default();
+        let tokenizer = tokenizer::from_settings(&settings.tokenizer);
+        let load = |settings: &Settings| load_or_count(&file, "prefix", &cache_folder, settings, tokenizer.as_ref()).unwrap();
+
+        let counted = load(&settings);
+        let cached = load(&settings);
+        assert!(!counted.hit && cached.hit);
+        assert_eq!((cached.counts.chapters[0].tokens, cached.counts.chapters[0].words["cat"]), (6, 1));
+        assert_eq!(cached.cache_name, counted.cache_name);
+
+        fs::write(&file, "The cat sat on the hat").unwrap();
+        let changed = load(&settings);
+        assert!(!changed.hit);
+        assert_eq!(changed.counts.chapters[0].words.get("mat"), None);
+        assert_ne!(changed.cache_name, counted.cache_name);
+
+        settings.normalization.mode = NormalizationMode::Stem;
+        assert!(!load(&settings).hit);
+        assert!(load(&settings).hit);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn stale_cache_files_are_removed() {
+        let root = folder("prune");
+        let cache_folder = root.join("cache");
+        let (a, b) = (root.join("a.txt"), root.join("b.txt"));
+        fs::write(&a, "one two").unwrap();
+        fs::write(&b, "three four").unwrap();
+        let settings = Settings::default();
+
+        digest_and_count(&root, &[a.clone(), b.clone()], &settings, &cache_folder).unwrap();
+        let first = cache_files(&cache_folder);
+        assert_eq!(first.len(), 2);
+
+        // the entry of the changed file is replaced, the other one is kept
+        fs::write(&a, "one two five").unwrap();
+        digest_and_count(&root, &[a.clone(), b.clone()], &settings, &cache_folder).unwrap();
+        let second = cache_files(&cache_folder);
+        assert_eq!(second.len(), 2);
+        assert_eq!(second.iter().filter(|name| first.contains(name)).count(), 1);
+
+        // the entry of a file that is no longer analyzed is removed, the files of other folders are left alone
+        fs::write(cache_folder.join("other.json"), "{}").unwrap();
+        let (corpus, _) = digest_and_count(&root, std::slice::from_ref(&a), &settings, &cache_folder).unwrap();
+        assert_eq!(corpus.token_count(), 3);
+        assert_eq!(cache_files(&cache_folder).len(), 2);
+        assert!(cache_folder.join("other.json").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
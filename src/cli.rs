@@ -0,0 +1,137 @@
+use std::path::Path;
+
+use word_frequency_analysis::output::{OutputFormat, FORMATS};
+use word_frequency_analysis::settings::ANALYSES;
+
+pub const USAGE: &str = "\
+Usage: word-frequency-analysis [OPTIONS] [INPUT_FOLDER]
+
+Run without any arguments to start the interactive mode.
+
+Options:
+  -i, --input <FOLDER>       Folder containing the txt (documents) files to analyze
+  -s, --settings <FILE>      Path to a settings.json file (optional)
+  -o, --output <FOLDER>      Folder to write the results to, overrides the settings
+                             (default: <INPUT_FOLDER>/outputs)
+  -a, --analyses <LIST>      Comma-separated list of analyses to run, overrides the settings
+                             (default: word_frequency,tf_idf)
+                             Available: word_frequency, tf_idf, dispersion,
+                                        lexical_diversity, zipf, ngrams,
+                                        collocations, keyness, concordance, charts,
+                                        wordcloud, report
+  -f, --formats <LIST>       Comma-separated list of formats of the word frequency, phrase
+                             and TF-IDF results, overrides the settings (default: csv)
+                             Available: csv, json, ndjson, sqlite, parquet
+  -h, --help                 Print this help message
+  -V, --version              Print the version";
+
+// what the command line asked for
+pub enum Command {
+    Run(CliArgs),
+    Help,
+    Version,
+}
+
+pub struct CliArgs {
+    pub input: String,
+    pub settings: String,
+    pub output: Option<String>,
+    pub analyses: Option<Vec<String>>,
+    pub formats: Option<Vec<OutputFormat>>,
+}
+
+// every option name, so that a missing value is not mistaken for the next option
+const FLAGS: [&str; 14] = ["-i", "--input", "-s", "--settings", "-o", "--output", "-a", "--analyses", "-f", "--formats", "-h", "--help", "-V", "--version"];
+
+// take the value given with --flag=value, or else the argument that follows the flag, or fail with a message naming the flag
+// values may start with '-' (e.g. a folder named "-drafts"), as long as they are not an option name
+fn next_value(args: &mut impl Iterator<Item = String>, inline: &mut Option<String>, flag: &str) -> Result<String, String> {
+    if let Some(value) = inline.take() {
+        return Ok(value.trim_matches('"').to_string());
+    }
+    match args.next() {
+        Some(value) if !FLAGS.contains(&value.as_str()) => Ok(value.trim_matches('"').to_string()),
+        _ => Err(format!("Missing value for {}", flag)),
+    }
+}
+
+fn parse_analyses(list: &str) -> Result<Vec<String>, String> {
+    let mut result: Vec<String> = Vec::new();
+    for name in list.split(',').map(|name| name.trim()).filter(|name| !name.is_empty()) {
+        if !ANALYSES.contains(&name) {
+            return Err(format!("Unknown analysis \"{}\" (available: {})", name, ANALYSES.join(", ")));
+        }
+        if !result.iter().any(|existing| existing == name) {
+            result.push(name.to_string());
+        }
+    }
+    if result.is_empty() {
+        return Err("No analysis selected with --analyses".to_string());
+    }
+
+    Ok(result)
+}
+
+fn parse_formats(list: &str) -> Result<Vec<OutputFormat>, String> {
+    let mut result: Vec<OutputFormat> = Vec::new();
+    for name in list.split(',').map(|name| name.trim()).filter(|name| !name.is_empty()) {
+        let format: OutputFormat = name.parse().map_err(|_| format!("Unknown format \"{}\" (available: {})", name, FORMATS.join(", ")))?;
+        if !result.contains(&format) {
+            result.push(format);
+        }
+    }
+    if result.is_empty() {
+        return Err("No format selected with --formats".to_string());
+    }
+
+    Ok(result)
+}
+
+// parse the arguments (without the program name). Returns None if there are no arguments, meaning interactive mode
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Command>, String> {
+    let mut args = args.into_iter().peekable();
+    if args.peek().is_none() {
+        return Ok(None);
+    }
+
+    let mut input: Option<String> = None;
+    let mut settings = String::new();
+    let mut output: Option<String> = None;
+    let mut analyses: Option<Vec<String>> = None;
+    let mut formats: Option<Vec<OutputFormat>> = None;
+
+    while let Some(arg) = args.next() {
+        // --flag=value
+        let (arg, mut inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (arg, None),
+        };
+        match arg.as_str() {
+            "-h" | "--help" | "-V" | "--version" if inline.is_some() => return Err(format!("Option {} does not take a value", arg)),
+            "-h" | "--help" => return Ok(Some(Command::Help)),
+            "-V" | "--version" => return Ok(Some(Command::Version)),
+            "-i" | "--input" => input = Some(next_value(&mut args, &mut inline, &arg)?),
+            "-s" | "--settings" => settings = next_value(&mut args, &mut inline, &arg)?,
+            "-o" | "--output" => output = Some(next_value(&mut args, &mut inline, &arg)?),
+            "-a" | "--analyses" => analyses = Some(parse_analyses(&next_value(&mut args, &mut inline, &arg)?)?),
+            "-f" | "--formats" => formats = Some(parse_formats(&next_value(&mut args, &mut inline, &arg)?)?),
+            _ if arg.starts_with('-') => return Err(format!("Unknown option \"{}\"", arg)),
+            _ => {
+                if input.is_some() {
+                    return Err(format!("Unexpected argument \"{}\"", arg));
+                }
+                input = Some(arg.trim_matches('"').to_string());
+            }
+        }
+    }
+
+    let input = input.ok_or("Missing input folder (use --input <FOLDER>)")?;
+    if !Path::new(&input).is_dir() {
+        return Err(format!("Input folder \"{}\" does not exist or is not a folder", input));
+    }
+    if !settings.is_empty() && !Path::new(&settings).is_file() {
+        return Err(format!("Settings file \"{}\" does not exist", settings));
+    }
+
+    Ok(Some(Command::Run(CliArgs { input, settings, output, analyses, formats })))
+}
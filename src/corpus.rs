@@ -0,0 +1,156 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::normalizer::TermForm;
+use crate::utils::utils;
+
+// <file name, chapters<word, value>>
+pub type ChapterFrequencies<T> = HashMap<String, Vec<HashMap<String, T>>>;
+
+/// A single document unit: one chapter of a file, or the whole file if it has no chapter separator
+#[derive(Debug, Clone, Default)]
+pub struct Chapter {
+    /// Raw text of the chapter, used for phrase matching
+    pub text: String,
+    /// Number of words. Kept when the words themselves are not loaded (chapters read from the cache, see [`crate::cache`])
+    pub tokens: usize,
+    /// Words after punctuation trimming and capitalization normalization
+    pub words: Vec<String>,
+    /// Stems or lemmas of `words` (same length), or empty if no normalization was applied
+    pub normalized: Vec<String>,
+}
+
+impl Chapter {
+    /// The words in the requested form. Falls back to the raw words if there is no normalized form
+    pub fn terms(&self, form: TermForm) -> &[String] {
+        match form {
+            TermForm::Normalized if !self.normalized.is_empty() => &self.normalized,
+            _ => &self.words,
+        }
+    }
+}
+
+/// One input file, split into chapters
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    pub name: String,
+    pub path: PathBuf,
+    /// Encoding the file was decoded from (e.g. "UTF-8", "windows-1252")
+    pub encoding: String,
+    /// Size of the file in bytes, as it was read
+    pub size: u64,
+    /// SHA-256 of the bytes that were read, in hex
+    pub sha256: String,
+    pub chapters: Vec<Chapter>,
+}
+
+/// All digested input files, along with the raw and normalized vocabularies
+#[derive(Debug, Clone, Default)]
+pub struct Corpus {
+    pub documents: Vec<Document>,
+    /// Unique words before capitalization normalization
+    pub vocabulary: HashSet<String>,
+    /// Unique words after capitalization normalization
+    pub normalized_vocabulary: HashSet<String>,
+}
+
+impl Corpus {
+    pub fn document(&self, name: &str) -> Option<&Document> {
+        self.documents.iter().find(|document| document.name == name)
+    }
+
+    /// Document names, sorted alphabetically
+    pub fn document_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.documents.iter().map(|document| document.name.clone()).collect();
+        names.sort();
+        names
+    }
+
+    pub fn has_chapters(&self) -> bool {
+        self.documents.iter().any(|document| document.chapters.len() > 1)
+    }
+
+    /// Number of words in all the chapters of all the documents
+    pub fn token_count(&self) -> usize {
+        self.documents.iter().flat_map(|document| &document.chapters).map(|chapter| chapter.tokens).sum()
+    }
+}
+
+/// Word (and target phrase) counts for each chapter of each file
+#[derive(Debug, Clone, Default)]
+pub struct FrequencyTable {
+    pub files: ChapterFrequencies<usize>,
+}
+
+impl FrequencyTable {
+    /// File names, sorted alphabetically
+    pub fn file_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.files.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn has_chapters(&self) -> bool {
+        self.files.values().any(|chapters| chapters.len() > 1)
+    }
+
+    /// Number of document units (chapters across all files)
+    pub fn document_count(&self) -> usize {
+        self.files.values().map(|chapters| chapters.len()).sum()
+    }
+
+    /// Counts summed over the chapters of each file: <file name, <word, frequency>>
+    pub fn file_totals(&self) -> HashMap<String, HashMap<String, usize>> {
+        let mut result: HashMap<String, HashMap<String, usize>> = HashMap::new();
+        for (file_name, chapters) in &self.files {
+            let mut file_total: HashMap<String, usize> = HashMap::new();
+            for chapter in chapters {
+                for (word, freq) in chapter {
+                    *file_total.entry(word.clone()).or_insert(0) += *freq;
+                }
+            }
+            result.insert(file_name.clone(), file_total);
+        }
+        result
+    }
+
+    /// Counts summed over all the chapters of all the files: <word, frequency>
+    pub fn corpus_totals(&self) -> HashMap<String, usize> {
+        let mut result: HashMap<String, usize> = HashMap::new();
+        for chapter in self.files.values().flatten() {
+            for (word, freq) in chapter {
+                *result.entry(word.clone()).or_insert(0) += *freq;
+            }
+        }
+        result
+    }
+
+    /// A copy of the table with the stopwords removed from every chapter
+    pub fn without_stopwords(&self) -> FrequencyTable {
+        FrequencyTable { files: utils::remove_stopwords_with_chapters(&self.files) }
+    }
+}
+
+/// TF-IDF value of each word for each chapter of each file
+#[derive(Debug, Clone, Default)]
+pub struct TfIdfTable {
+    pub files: ChapterFrequencies<f64>,
+}
+
+impl TfIdfTable {
+    /// File names, sorted alphabetically
+    pub fn file_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.files.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// All words with a TF-IDF value in any chapter
+    pub fn words(&self) -> HashSet<String> {
+        self.files.values().flatten().flat_map(|chapter| chapter.keys()).cloned().collect()
+    }
+}
@@ -0,0 +1,56 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Everything that can go wrong during a run. Each variant names the file it is about, if there is one
+#[derive(Debug)]
+pub enum Error {
+    /// An input file or folder could not be read
+    Io { path: PathBuf, source: io::Error },
+    /// The settings file is missing, malformed, or has invalid values
+    Settings { path: PathBuf, reason: String },
+    /// An input file could not be decoded as text
+    Encoding { path: PathBuf, reason: String },
+    /// A result file or folder could not be written
+    Output { path: PathBuf, source: io::Error },
+    /// A reference frequency table could not be parsed
+    Reference { path: PathBuf, reason: String },
+    /// No file in the input folder matched the include patterns (or none could be read)
+    NoFiles { path: PathBuf, include: Vec<String> },
+    /// A result table could not be built in memory
+    Table { source: csv::Error },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "Could not read \"{}\": {}", path.display(), source),
+            // settings built in code (not loaded from a file) have no path
+            Error::Settings { path, reason } if path.as_os_str().is_empty() => write!(f, "Invalid settings: {}", reason),
+            Error::Settings { path, reason } => write!(f, "Invalid settings file \"{}\": {}", path.display(), reason),
+            Error::Encoding { path, reason } => write!(f, "Could not decode \"{}\": {}", path.display(), reason),
+            Error::Output { path, source } => write!(f, "Could not write \"{}\": {}", path.display(), source),
+            Error::Reference { path, reason } => write!(f, "Invalid reference frequency table \"{}\": {}", path.display(), reason),
+            Error::NoFiles { path, include } => write!(f, "No readable files matching {:?} found in \"{}\"", include, path.display()),
+            Error::Table { source } => write!(f, "Could not build a result table: {}", source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } | Error::Output { source, .. } => Some(source),
+            Error::Table { source } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(source: csv::Error) -> Self {
+        Error::Table { source }
+    }
+}
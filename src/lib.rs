@@ -0,0 +1,145 @@
+//! Word frequency, TF-IDF, dispersion, lexical diversity, Zipf, n-gram, collocation, keyness and concordance analyses of text files,
+//! with SVG charts, word clouds and a self-contained HTML report.
+//!
+//! The usual pipeline is [`digest`] → [`count`] → [`tf_idf`]:
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use word_frequency_analysis::Settings;
+//!
+//! let folder = Path::new("books");
+//! let settings = Settings::load("settings.json")?;
+//! let files = word_frequency_analysis::discover(folder, &settings)?;
+//! let corpus = word_frequency_analysis::digest(folder, &files, &settings)?;
+//! let frequencies = word_frequency_analysis::count(&corpus, &settings.phrases, settings.normalization.report);
+//! let tf_idf = word_frequency_analysis::tf_idf(&frequencies.without_stopwords());
+//! # Ok::<(), word_frequency_analysis::Error>(())
+//! ```
+
+use std::path::{Path, PathBuf};
+
+pub mod utils {
+    #[allow(clippy::module_inception)]
+    pub mod utils;
+    pub mod tables;
+    pub mod files;
+    pub mod encoding;
+    pub mod graph;
+    pub mod wordcloud;
+    pub mod json;
+    pub mod sqlite;
+    pub mod columnar;
+    pub mod manifest;
+}
+
+pub mod analyses {
+    pub mod word_frequency;
+    pub mod tf_idf;
+    pub mod dispersion;
+    pub mod lexical_diversity;
+    pub mod zipf;
+    pub mod ngrams;
+    pub mod collocations;
+    pub mod keyness;
+    pub mod concordance;
+    pub mod charts;
+    pub mod wordcloud;
+    pub mod report;
+    pub mod export;
+    #[cfg(test)]
+    pub(crate) mod test_util;
+}
+
+pub mod cache;
+pub mod corpus;
+pub mod error;
+pub mod normalizer;
+pub mod output;
+pub mod settings;
+pub mod tokenizer;
+
+pub use corpus::{Chapter, ChapterFrequencies, Corpus, Document, FrequencyTable, TfIdfTable};
+pub use error::{Error, Result};
+pub use normalizer::TermForm;
+pub use output::TableLayout;
+pub use settings::Settings;
+pub use tokenizer::Tokenizer;
+
+/// Recursively list the files in `folder` matching the `include` patterns and none of the `exclude` patterns
+pub fn discover(folder: &Path, settings: &Settings) -> Result<Vec<PathBuf>> {
+    utils::files::discover_files(folder, &settings.include, &settings.exclude)
+}
+
+/// Read and decode the files, split them into chapters and words, normalize the capitalization, and stem or lemmatize the words if enabled.
+/// Documents are named after their path relative to `root`. Unreadable files are skipped with a warning
+pub fn digest(root: &Path, files: &[PathBuf], settings: &Settings) -> Result<Corpus> {
+    let tokenizer = tokenizer::from_settings(&settings.tokenizer);
+    utils::utils::digest_files(root, files, settings, tokenizer.as_ref())
+}
+
+/// Same as [`digest`], but with your own [`Tokenizer`] instead of the one selected in the settings
+pub fn digest_with_tokenizer(root: &Path, files: &[PathBuf], settings: &Settings, tokenizer: &dyn Tokenizer) -> Result<Corpus> {
+    utils::utils::digest_files(root, files, settings, tokenizer)
+}
+
+/// Same as [`digest`] then [`count`], but the counts of the files that have not changed since the last run are read from `cache_folder`.
+/// The chapters of the corpus only have their number of words, see [`cache::CACHED_ANALYSES`] for what can run on it
+pub fn digest_cached(root: &Path, files: &[PathBuf], settings: &Settings, cache_folder: &Path) -> Result<(Corpus, FrequencyTable)> {
+    cache::digest_and_count(root, files, settings, cache_folder)
+}
+
+/// Count the words (in the requested form) and target phrases in each chapter of each document
+pub fn count(corpus: &Corpus, phrases: &[String], form: TermForm) -> FrequencyTable {
+    analyses::word_frequency::count(corpus, phrases, form)
+}
+
+/// Calculate TF-IDF using the chapters as document units. Stopwords are kept, use [`FrequencyTable::without_stopwords`] to drop them first
+pub fn tf_idf(frequencies: &FrequencyTable) -> TfIdfTable {
+    analyses::tf_idf::calculate_tf_idf(frequencies)
+}
+
+/// Juilland's D, Gries' DP, range and coefficient of variation of every word over the chapters of the corpus
+pub fn dispersion(corpus: &Corpus, frequencies: &FrequencyTable) -> Vec<analyses::dispersion::Dispersion> {
+    analyses::dispersion::calculate_dispersion(frequencies, &analyses::dispersion::chapter_sizes(corpus))
+}
+
+/// Token and type counts, TTR, root TTR, hapax and dis legomena, Yule's K, MTLD and HD-D of every chapter, file and of the corpus
+pub fn lexical_diversity(corpus: &Corpus, settings: &Settings) -> Vec<analyses::lexical_diversity::LexicalDiversity> {
+    analyses::lexical_diversity::calculate_lexical_diversity(corpus, settings.normalization.report, &settings.lexical_diversity)
+}
+
+/// Fit Zipf's law (maximum likelihood and log-log regression) and Heaps' law on each document and on the corpus
+pub fn zipf(corpus: &Corpus, settings: &Settings) -> Vec<analyses::zipf::ZipfFit> {
+    analyses::zipf::calculate_zipf(corpus, settings.normalization.report)
+}
+
+/// Count the n-grams of length `n` in each chapter of each document, using the n-gram settings (minimum count, stopword boundaries)
+pub fn ngrams(corpus: &Corpus, n: usize, settings: &Settings) -> FrequencyTable {
+    analyses::ngrams::count(corpus, n, settings.normalization.report, settings.ngrams.min_count, settings.ngrams.stopword_boundary)
+}
+
+/// Score the word pairs at most `window` words apart (1 = adjacent pairs) with PMI, log-likelihood, t-score and Dice, in each document and in the whole corpus
+pub fn collocations(corpus: &Corpus, window: usize, settings: &Settings) -> analyses::collocations::CollocationTable {
+    analyses::collocations::calculate_collocations(corpus, settings.normalization.report, window, settings.collocations.min_freq, settings.collocations.skip_stopwords)
+}
+
+/// Compare the word totals of `frequencies` (without the target phrases) with those of a reference (a folder digested with the same settings, or a saved frequency table)
+pub fn keyness(corpus: &Corpus, frequencies: &FrequencyTable, reference: &Path, settings: &Settings) -> Result<Vec<analyses::keyness::Keyword>> {
+    let (reference, reference_tokens) = analyses::keyness::load_reference(reference, settings)?;
+    let target = analyses::keyness::word_totals(frequencies, &settings.phrases);
+    Ok(analyses::keyness::calculate_keyness(&target, corpus.token_count(), &reference, reference_tokens, settings.keyness.min_frequency))
+}
+
+/// Keyword-in-context search: every hit of the queries in the raw text of each chapter, with the context around it
+pub fn concordance(corpus: &Corpus, queries: &[String], settings: &Settings) -> Result<Vec<analyses::concordance::ConcordanceLine>> {
+    analyses::concordance::search(corpus, queries, &settings.concordance)
+}
+
+/// The self-contained HTML report (run metadata, sortable tables and charts) of the corpus read from `input_folder`
+pub fn report(folder_name: &str, input_folder: &Path, corpus: &Corpus, frequencies: &FrequencyTable, settings: &Settings) -> Result<String> {
+    let tfidf = match frequencies.document_count() > 1 {
+        true => tf_idf(&frequencies.without_stopwords()),
+        false => TfIdfTable::default(),
+    };
+    analyses::report::build_report(folder_name, input_folder, corpus, frequencies, &tfidf, &zipf(corpus, settings), settings)
+}
@@ -1,108 +1,231 @@
-use std::path::PathBuf;
-use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-mod utils {
-    pub mod utils;
-    pub mod tables;
+use word_frequency_analysis::{analyses, cache, utils, Corpus, Error, Settings, TfIdfTable};
+use word_frequency_analysis::analyses::wordcloud::WordCloudWeight;
+use word_frequency_analysis::output::{OutputFormat, OutputPaths};
+use word_frequency_analysis::utils::manifest::Timings;
+
+mod cli;
+
+use cli::Command;
+
+fn main() -> ExitCode {
+    match cli::parse_args(std::env::args().skip(1)) {
+        Ok(None) => interactive(),
+        Ok(Some(Command::Help)) => {
+            println!("{}", cli::USAGE);
+            ExitCode::SUCCESS
+        }
+        Ok(Some(Command::Version)) => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            ExitCode::SUCCESS
+        }
+        Ok(Some(Command::Run(args))) => {
+            match run(&args.input, &args.settings, args.output.as_deref().map(Path::new), args.analyses.as_deref(), args.formats.as_deref()) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(error) => {
+                    eprintln!("\x1b[31mError: {}\x1b[0m", error);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Err(message) => {
+            eprintln!("\x1b[31mError: {}\x1b[0m\n\n{}", message, cli::USAGE);
+            ExitCode::from(2)
+        }
+    }
 }
 
-mod analyses {
-    pub mod word_frequency;
-    pub mod tf_idf;
+// prompt for the folder and settings, run all analyses, then ask to restart
+fn interactive() -> ExitCode {
+    match prompt_loop() {
+        Ok(()) => ExitCode::SUCCESS,
+        // the terminal could not be read (e.g. it was closed)
+        Err(error) => {
+            eprintln!("\x1b[31mError: {}\x1b[0m", error);
+            ExitCode::FAILURE
+        }
+    }
 }
 
-fn main() {
-    println!("\x1b[1;36mText frequency analysis tool ~ by @codynhanpham\x1b[0m");
-    println!("https://github.com/codynhanpham/word-frequency-analysis\n\n");
-
-    // get folder directory
-    let folder_dir = utils::utils::get_folder_dir("Enter txt (documents) folder directory: ");
-    // list all .txt files in folder
-    let mut txt_files: Vec<PathBuf> = Vec::new();
-    for entry in fs::read_dir(&folder_dir).expect("Failed to read directory") {
-        let entry = entry.expect("Failed to read entry");
-        let path = entry.path();
-        if path.is_file() {
-            if path.extension().unwrap() == "txt" {
-                txt_files.push(path);
-            }
+fn prompt_loop() -> Result<(), Error> {
+    loop {
+        println!("\x1b[1;36mText frequency analysis tool ~ by @codynhanpham\x1b[0m");
+        println!("https://github.com/codynhanpham/word-frequency-analysis\n\n");
+
+        // get folder directory
+        let folder_dir = utils::utils::get_folder_dir("Enter txt (documents) folder directory: ")?;
+
+        // get settings.json file path
+        let settings = utils::utils::get_json_path("Enter the settings.json file path (leave empty for default): ")?;
+
+        if let Err(error) = run(&folder_dir, &settings, None, None, None) {
+            println!("\x1b[31mError: {}\x1b[0m", error);
         }
+
+        // wait for user input to exit/restart: blank to restart, anything else to exit
+        let input = utils::utils::get_input("Press enter to restart, or type anything to exit...")?;
+        if !input.is_empty() {
+            return Ok(());
+        }
+        clear_screen();
     }
-    let number_of_files = txt_files.len();
-    println!("\x1b[2m  --> Found \x1b[0;1m{}\x1b[0;2m txt files in folder\x1b[0m", number_of_files);
+}
 
-    // if no .txt files found, return main() for restart
-    if number_of_files == 0 {
-        println!("Please make sure there are .txt files in the folder\n");
-        utils::utils::get_input("Press enter to restart...");
-        // Restart
-        println!("Trying to clear screen...");
-        clearscreen::clear().expect("Failed to clear screen. The program should still work fine, though!\n\n");
-        return main();
+fn clear_screen() {
+    println!("Trying to clear screen...");
+    if clearscreen::clear().is_err() {
+        println!("Failed to clear screen. The program should still work fine, though!\n\n");
     }
-    
+}
 
-    // get settings.json file path
-    let settings = utils::utils::get_json_path("Enter the settings.json file path (leave empty for default): ");
+// the files that were read, and the size of the vocabulary
+fn print_corpus(corpus: &Corpus) {
+    let (vocabulary, normalized_vocabulary) = (corpus.vocabulary.len(), corpus.normalized_vocabulary.len());
+    for document in &corpus.documents {
+        println!("\x1b[2m  {} ({})\x1b[0m", document.name, document.encoding);
+    }
+    println!("There are {} unique words in the raw corpus", vocabulary);
+    println!("There are {} after being normalized ({}%)", normalized_vocabulary, (normalized_vocabulary as f64 / vocabulary as f64 * 100.0 * 100.0).round() / 100.0);
+}
 
-    // get target phrases
-    let phrases = utils::utils::get_phrases_from_json(&settings);
-    // get chapter separator: from settings.json or default to "<|eoc|>"
-    let chapter_separator = utils::utils::get_chapter_separator_from_json(&settings, "<|eoc|>".to_string());
+// output_dir, analyses, formats: the values given on the command line, if any, replace the ones from the settings
+fn run(folder_dir: &str, settings: &str, output_dir: Option<&Path>, analyses: Option<&[String]>, formats: Option<&[OutputFormat]>) -> Result<(), Error> {
+    // get target phrases, chapter separator, file patterns...: from settings.json or the defaults
+    let mut settings = Settings::load(settings)?;
+    if let Some(analyses) = analyses {
+        settings.analyses = analyses.to_vec();
+    }
+    if let Some(formats) = formats {
+        settings.output.formats = formats.to_vec();
+    }
+    let phrases = &settings.phrases;
 
+    // list all matching files in folder and its subfolders
+    let txt_files = word_frequency_analysis::discover(Path::new(folder_dir), &settings)?;
+    println!("\x1b[2m  --> Found \x1b[0;1m{}\x1b[0;2m txt files in folder\x1b[0m", txt_files.len());
+    if txt_files.is_empty() {
+        return Err(Error::NoFiles { path: PathBuf::from(folder_dir), include: settings.include.clone() });
+    }
+
+    let folder_name = Path::new(folder_dir)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "corpus".to_string());
+
+    // output folder: from the command line, else from the settings, else <input folder>/outputs
+    let output_dir = match output_dir {
+        Some(output_dir) => output_dir.to_path_buf(),
+        None if !settings.output.directory.is_empty() => PathBuf::from(&settings.output.directory),
+        None => Path::new(folder_dir).join("outputs"),
+    };
+    let run_info = utils::json::RunInfo { input_folder: PathBuf::from(folder_dir), started_at: chrono::Local::now() };
+    let paths = OutputPaths::new(&folder_name, &output_dir, &settings, run_info.started_at);
 
     println!("------------------------------------------------------------");
     println!("Analyzing folder: {}", folder_dir);
     println!("And scan for {} target phrases", phrases.len());
+    println!("Writing results to: {}", paths.directory.display());
     println!("------------------------------------------------------------");
 
-    // start time
+    // start time, and the time of each stage for the manifest
     let start = std::time::Instant::now();
+    let mut timings = Timings::default();
 
+    let uncached = cache::uncached_analyses(&settings);
+    if settings.cache.enabled && !uncached.is_empty() {
+        println!("\x1b[33m  WARNING: The cache is not used, these analyses need the text of the files: {}\x1b[0m", uncached.join(", "));
+    }
 
-    // Digest all files (Split by chapter if possible, and remove punctuations, split into words, and normalize capitalization)
-    let digest_data = utils::utils::digest_files(&txt_files, &chapter_separator);
-    let raw_data = digest_data.0;
-    let data = digest_data.1;
-    let corpus = digest_data.2;
-    let normalized_corpus = digest_data.3;
-    println!("There are {} unique words in the raw corpus", corpus.len());
-    println!("There are {} after being normalized ({}%)", normalized_corpus.len(), (normalized_corpus.len() as f64 / corpus.len() as f64 * 100.0 * 100.0).round() / 100.0);
+    let (corpus, frequencies) = match cache::usable(&settings) {
+        // Read the counts of the unchanged files from the cache, and only count the others
+        true => {
+            let cache_folder = settings.cache.folder(&output_dir);
+            let (corpus, frequencies) = timings.time("digest_cached", || word_frequency_analysis::digest_cached(Path::new(folder_dir), &txt_files, &settings, &cache_folder))?;
+            print_corpus(&corpus);
+            (corpus, frequencies)
+        }
+        false => {
+            // Digest all files (Split by chapter if possible, and remove punctuations, split into words, and normalize capitalization)
+            let corpus = timings.time("digest", || word_frequency_analysis::digest(Path::new(folder_dir), &txt_files, &settings))?;
+            print_corpus(&corpus);
+            let frequencies = timings.time("count", || word_frequency_analysis::count(&corpus, phrases, settings.normalization.report));
+            (corpus, frequencies)
+        }
+    };
 
     // Analysis
-    let word_freq_map = analyses::word_frequency::main(&folder_dir, raw_data, data, &phrases);
-    let _tf_idf = analyses::tf_idf::main(&folder_dir, &word_freq_map, &phrases);
+    let output = &settings.output;
+    if settings.runs("word_frequency") {
+        timings.time("word_frequency", || analyses::word_frequency::main(&paths, &frequencies, phrases, output))?;
+    }
+    let tf_idf = match settings.runs("tf_idf") {
+        true => timings.time("tf_idf", || analyses::tf_idf::main(&paths, &frequencies, phrases, output))?,
+        false => TfIdfTable::default(),
+    };
+    if output.long_csv() && (settings.runs("word_frequency") || settings.runs("tf_idf")) {
+        timings.time("long_table", || analyses::word_frequency::long_table(&paths, &frequencies, &tf_idf, phrases, output))?;
+    }
+    if settings.runs("word_frequency") || settings.runs("tf_idf") {
+        timings.time("export", || analyses::export::main(&paths, &corpus, &frequencies, &tf_idf, &settings, &run_info))?;
+    }
+    if settings.runs("dispersion") {
+        timings.time("dispersion", || analyses::dispersion::main(&paths, &corpus, &frequencies, output))?;
+    }
+    if settings.runs("lexical_diversity") {
+        timings.time("lexical_diversity", || analyses::lexical_diversity::main(&paths, &corpus, &settings))?;
+    }
+    let zipf_fits = if settings.runs("zipf") {
+        timings.time("zipf", || analyses::zipf::main(&paths, &corpus, settings.normalization.report, output))?
+    } else {
+        Vec::new()
+    };
+    if settings.runs("ngrams") {
+        timings.time("ngrams", || analyses::ngrams::main(&paths, &corpus, &settings))?;
+    }
+    if settings.runs("collocations") {
+        timings.time("collocations", || analyses::collocations::main(&paths, &corpus, &settings))?;
+    }
+    if settings.runs("keyness") {
+        timings.time("keyness", || analyses::keyness::main(&paths, &corpus, &frequencies, &settings))?;
+    }
+    if settings.runs("concordance") {
+        timings.time("concordance", || analyses::concordance::main(&paths, &corpus, &settings))?;
+    }
+
+    // the charts, word clouds and report reuse the TF-IDF (no stopwords) and the Zipf fits, computed here if those analyses did not run
+    let tf_idf_needed = settings.runs("charts") || settings.runs("report") || (settings.runs("wordcloud") && settings.wordcloud.weight == WordCloudWeight::TfIdf);
+    let tf_idf = match tf_idf.is_empty() && tf_idf_needed && frequencies.document_count() > 1 {
+        true => timings.time("tf_idf", || word_frequency_analysis::tf_idf(&frequencies.without_stopwords())),
+        false => tf_idf,
+    };
+    let zipf_fits = match zipf_fits.is_empty() && (settings.runs("charts") || settings.runs("report")) {
+        true => timings.time("zipf", || word_frequency_analysis::zipf(&corpus, &settings)),
+        false => zipf_fits,
+    };
+    if settings.runs("charts") {
+        timings.time("charts", || analyses::charts::main(&paths, &frequencies, &tf_idf, &zipf_fits, &settings))?;
+    }
+    if settings.runs("wordcloud") {
+        timings.time("wordcloud", || analyses::wordcloud::main(&paths, &frequencies, &tf_idf, &settings))?;
+    }
+    if settings.runs("report") {
+        timings.time("report", || analyses::report::main(&paths, Path::new(folder_dir), &corpus, &frequencies, &tf_idf, &zipf_fits, &settings))?;
+    }
 
 
     // Do more here
 
 
+    // which inputs and settings produced the outputs above
+    utils::manifest::write_manifest(&paths, &corpus, &settings, &run_info, &timings)?;
+
+
     // end time
     let duration = start.elapsed();
     println!("------------------------------------------------------------");
     println!("Total time taken: {} ms\n", duration.as_millis());
 
-    // drop all data
-    drop(corpus);
-    drop(word_freq_map);
-    drop(_tf_idf);
-    drop(txt_files);
-    drop(folder_dir);
-    drop(settings);
-    drop(phrases);
-    drop(chapter_separator);
-    drop(start);
-    drop(duration);
-    drop(normalized_corpus);
-    drop(number_of_files);
-
-    // wait for user input to exit/restart: blank to restart, anything else to exit
-    let input = utils::utils::get_input("Press enter to restart, or type anything to exit...");
-    if input == "" {
-        drop(input);
-        // Restart
-        println!("Trying to clear screen...");
-        clearscreen::clear().expect("Failed to clear screen. The program should still work fine, though!\n\n");
-        return main();
-    }
-}
\ No newline at end of file
+    Ok(())
+}
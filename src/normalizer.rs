@@ -0,0 +1,280 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+const LEMMAS_EN_STRING: &str = include_str!("utils/lemmas_en.txt");
+
+/// Optional normalization applied to the words after capitalization normalization
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NormalizationMode {
+    None,
+    /// Snowball stemmer ("running" -> "run", "studies" -> "studi")
+    Stem,
+    /// Dictionary-based lemmatizer ("ran" -> "run", "children" -> "child")
+    Lemma,
+}
+
+/// Which form of the words the frequency tables report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TermForm {
+    /// The words as they appear in the text (after capitalization normalization)
+    Raw,
+    /// The stems or lemmas
+    Normalized,
+}
+
+/// The "normalization" section of settings.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NormalizationSettings {
+    pub mode: NormalizationMode,
+    /// Language of the stemmer and of the built-in lemma rules, e.g. "english", "french", "german"
+    pub language: String,
+    /// Optional file of "<inflected form> <lemma>" lines, added to (and overriding) the built-in dictionary
+    pub lemma_file: String,
+    /// Which form the frequency tables report
+    pub report: TermForm,
+}
+
+impl Default for NormalizationSettings {
+    fn default() -> Self {
+        NormalizationSettings {
+            mode: NormalizationMode::None,
+            language: "english".to_string(),
+            lemma_file: String::new(),
+            report: TermForm::Normalized,
+        }
+    }
+}
+
+pub fn algorithm_from_language(language: &str) -> std::result::Result<Algorithm, String> {
+    let algorithm = match language.trim().to_lowercase().as_str() {
+        "arabic" => Algorithm::Arabic,
+        "danish" => Algorithm::Danish,
+        "dutch" => Algorithm::Dutch,
+        "english" | "porter" | "snowball" => Algorithm::English,
+        "finnish" => Algorithm::Finnish,
+        "french" => Algorithm::French,
+        "german" => Algorithm::German,
+        "greek" => Algorithm::Greek,
+        "hungarian" => Algorithm::Hungarian,
+        "italian" => Algorithm::Italian,
+        "norwegian" => Algorithm::Norwegian,
+        "portuguese" => Algorithm::Portuguese,
+        "romanian" => Algorithm::Romanian,
+        "russian" => Algorithm::Russian,
+        "spanish" => Algorithm::Spanish,
+        "swedish" => Algorithm::Swedish,
+        "tamil" => Algorithm::Tamil,
+        "turkish" => Algorithm::Turkish,
+        _ => return Err(format!("unsupported normalization language \"{}\"", language)),
+    };
+    Ok(algorithm)
+}
+
+fn is_english(language: &str) -> bool {
+    matches!(language.trim().to_lowercase().as_str(), "english" | "porter" | "snowball")
+}
+
+// consonant + vowel + consonant at the end of the stem, e.g. "hop" in "hoping"
+fn ends_with_cvc(stem: &str) -> bool {
+    let is_vowel = |c: char| "aeiou".contains(c);
+    let chars: Vec<char> = stem.chars().collect();
+    let n = chars.len();
+    n >= 3 && !is_vowel(chars[n - 3]) && is_vowel(chars[n - 2]) && !is_vowel(chars[n - 1]) && !"wxy".contains(chars[n - 1])
+}
+
+/// Dictionary-based lemmatizer. For English, regular inflections are also undone by rules,
+/// but only if the resulting lemma is itself a word of the corpus or of the dictionary
+pub struct Lemmatizer {
+    dictionary: HashMap<String, String>,
+    // lemmas of the dictionary, also accepted as results of the rules
+    known_lemmas: HashSet<String>,
+    english_rules: bool,
+}
+
+fn parse_lemma_lines(text: &str, dictionary: &mut HashMap<String, String>) {
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split(|c: char| c == '\t' || c == ',' || c.is_whitespace()).filter(|field| !field.is_empty());
+        if let (Some(form), Some(lemma)) = (fields.next(), fields.next()) {
+            dictionary.insert(form.to_lowercase(), lemma.to_lowercase());
+        }
+    }
+}
+
+impl Lemmatizer {
+    pub fn new(language: &str) -> Self {
+        let mut dictionary: HashMap<String, String> = HashMap::new();
+        let english_rules = is_english(language);
+        if english_rules {
+            parse_lemma_lines(LEMMAS_EN_STRING, &mut dictionary);
+        }
+        let known_lemmas = dictionary.values().cloned().collect();
+        Lemmatizer { dictionary, known_lemmas, english_rules }
+    }
+
+    /// Add the "<inflected form> <lemma>" lines of a file (separated by tabs, commas or spaces, "#" for comments)
+    pub fn load_file(&mut self, path: &Path) -> Result<()> {
+        let text = fs::read_to_string(path).map_err(|source| Error::Io { path: path.to_path_buf(), source })?;
+        parse_lemma_lines(&text, &mut self.dictionary);
+        self.known_lemmas = self.dictionary.values().cloned().collect();
+        Ok(())
+    }
+
+    // candidate lemmas for regular English inflections, most specific first
+    fn rule_candidates(word: &str) -> Vec<String> {
+        let mut candidates: Vec<String> = Vec::new();
+        let undouble = |stem: &str| -> Option<String> {
+            let chars: Vec<char> = stem.chars().collect();
+            let n = chars.len();
+            (n >= 3 && chars[n - 1] == chars[n - 2] && !"aeiouls".contains(chars[n - 1])).then(|| chars[..n - 1].iter().collect())
+        };
+
+        if let Some(stem) = word.strip_suffix("ies").filter(|stem| stem.chars().count() >= 2) {
+            candidates.push(format!("{}y", stem));
+        }
+        // "boxes" -> "box", but "hopes" -> "hope"
+        if let Some(stem) = word.strip_suffix("es").filter(|stem| ["s", "x", "z", "ch", "sh", "o"].iter().any(|end| stem.ends_with(end))) {
+            candidates.push(stem.to_string());
+        }
+        if let Some(stem) = word.strip_suffix('s').filter(|stem| !stem.ends_with('s')) {
+            candidates.push(stem.to_string());
+        }
+        if let Some(stem) = word.strip_suffix("ied").filter(|stem| stem.chars().count() >= 2) {
+            candidates.push(format!("{}y", stem));
+        }
+        for suffix in ["ed", "ing"] {
+            if let Some(stem) = word.strip_suffix(suffix).filter(|stem| stem.chars().count() >= 2) {
+                // "running" -> "run"
+                candidates.extend(undouble(stem));
+                // a single consonant after a short vowel is usually doubled, so "hoping" is more likely "hope" than "hop"
+                if ends_with_cvc(stem) {
+                    candidates.push(format!("{}e", stem));
+                    candidates.push(stem.to_string());
+                } else {
+                    candidates.push(stem.to_string());
+                    candidates.push(format!("{}e", stem));
+                }
+            }
+        }
+
+        candidates
+    }
+
+    /// Lemma of a lowercase word, or the word itself if it is not known
+    pub fn lemmatize(&self, word: &str, vocabulary: &HashSet<String>) -> String {
+        if let Some(lemma) = self.dictionary.get(word) {
+            return lemma.clone();
+        }
+        if self.english_rules && word.chars().count() > 3 {
+            for candidate in Lemmatizer::rule_candidates(word) {
+                if vocabulary.contains(&candidate) || self.known_lemmas.contains(&candidate) {
+                    return candidate;
+                }
+            }
+        }
+        word.to_string()
+    }
+}
+
+/// Turns a word into its stem or lemma
+pub enum Normalizer {
+    Stem(Stemmer),
+    Lemma(Lemmatizer),
+}
+
+impl Normalizer {
+    /// Build the normalizer selected in the settings, or None if the mode is "none"
+    pub fn from_settings(settings: &NormalizationSettings) -> Result<Option<Normalizer>> {
+        let settings_error = |reason: String| Error::Settings { path: PathBuf::new(), reason };
+        match settings.mode {
+            NormalizationMode::None => Ok(None),
+            NormalizationMode::Stem => {
+                let algorithm = algorithm_from_language(&settings.language).map_err(settings_error)?;
+                Ok(Some(Normalizer::Stem(Stemmer::create(algorithm))))
+            }
+            NormalizationMode::Lemma => {
+                let mut lemmatizer = Lemmatizer::new(&settings.language);
+                if !settings.lemma_file.is_empty() {
+                    lemmatizer.load_file(Path::new(&settings.lemma_file))?;
+                }
+                Ok(Some(Normalizer::Lemma(lemmatizer)))
+            }
+        }
+    }
+
+    /// Normalize a word. Capitalized words are names (they never appear in lowercase in the corpus) and are left as they are
+    /// `vocabulary` is the set of all (capitalization normalized) words in the corpus
+    pub fn normalize(&self, word: &str, vocabulary: &HashSet<String>) -> String {
+        if word.chars().next().is_some_and(char::is_uppercase) {
+            return word.to_string();
+        }
+        match self {
+            Normalizer::Stem(stemmer) => stemmer.stem(word).into_owned(),
+            Normalizer::Lemma(lemmatizer) => lemmatizer.lemmatize(word, vocabulary),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vocabulary(words: &[&str]) -> HashSet<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn stemmer() {
+        let normalizer = Normalizer::Stem(Stemmer::create(algorithm_from_language("English").unwrap()));
+        let empty = HashSet::new();
+        assert_eq!(normalizer.normalize("running", &empty), "run");
+        assert_eq!(normalizer.normalize("studies", &empty), "studi");
+        // capitalized words are names, left as they are
+        assert_eq!(normalizer.normalize("Running", &empty), "Running");
+        assert!(algorithm_from_language("klingon").is_err());
+    }
+
+    #[test]
+    fn dictionary_lemmas() {
+        let lemmatizer = Lemmatizer::new("english");
+        let empty = HashSet::new();
+        assert_eq!(lemmatizer.lemmatize("ran", &empty), "run");
+        assert_eq!(lemmatizer.lemmatize("children", &empty), "child");
+        assert_eq!(lemmatizer.lemmatize("unknownword", &empty), "unknownword");
+    }
+
+    #[test]
+    fn rule_lemmas_need_a_known_word() {
+        let lemmatizer = Lemmatizer::new("english");
+        let corpus = vocabulary(&["hope", "hop", "box", "study", "walk"]);
+        assert_eq!(lemmatizer.lemmatize("hoping", &corpus), "hope");
+        assert_eq!(lemmatizer.lemmatize("hopping", &corpus), "hop");
+        assert_eq!(lemmatizer.lemmatize("boxes", &corpus), "box");
+        assert_eq!(lemmatizer.lemmatize("studies", &corpus), "study");
+        assert_eq!(lemmatizer.lemmatize("walked", &corpus), "walk");
+        // "glorps" -> "glorp" is only accepted if "glorp" is a word of the corpus
+        assert_eq!(lemmatizer.lemmatize("glorps", &corpus), "glorps");
+        assert_eq!(lemmatizer.lemmatize("glorps", &vocabulary(&["glorp"])), "glorp");
+    }
+
+    #[test]
+    fn other_languages_only_use_the_dictionary() {
+        let mut lemmatizer = Lemmatizer::new("french");
+        assert_eq!(lemmatizer.lemmatize("walked", &vocabulary(&["walk"])), "walked");
+        parse_lemma_lines("# comment\nchevaux\tcheval\nYeux, Œil\n", &mut lemmatizer.dictionary);
+        assert_eq!(lemmatizer.lemmatize("chevaux", &HashSet::new()), "cheval");
+        assert_eq!(lemmatizer.lemmatize("yeux", &HashSet::new()), "œil");
+    }
+}
@@ -0,0 +1,204 @@
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::settings::Settings;
+
+// placeholders of the file name template
+const PLACEHOLDERS: [&str; 4] = ["{folder}", "{analysis}", "{timestamp}", "{settings_hash}"];
+
+/// Shape of the word frequency and TF-IDF tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TableLayout {
+    /// One row per word, one column per file (or chapter)
+    Wide,
+    /// One row per (file, chapter, term): tidy data for R, pandas, spreadsheets with many chapters...
+    Long,
+    /// Both of the above
+    Both,
+}
+
+impl TableLayout {
+    pub fn wide(self) -> bool {
+        matches!(self, TableLayout::Wide | TableLayout::Both)
+    }
+
+    pub fn long(self) -> bool {
+        matches!(self, TableLayout::Long | TableLayout::Both)
+    }
+}
+
+/// Names of all the formats that can be listed in `formats`
+pub const FORMATS: [&str; 5] = ["csv", "json", "ndjson", "sqlite", "parquet"];
+
+/// File formats of the word frequency, phrase and TF-IDF results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// The CSV tables, in the chosen layout
+    Csv,
+    /// A single JSON document: metadata, then file → chapter → term
+    Json,
+    /// JSON Lines: a metadata line, then one line per chapter
+    Ndjson,
+    /// A SQLite database with one table per kind of data (documents, chapters, terms, counts...)
+    Sqlite,
+    /// Apache Parquet files in long layout (frequencies, phrases, TF-IDF), with integer and float columns
+    Parquet,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        match name {
+            "csv" => Ok(OutputFormat::Csv),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "sqlite" => Ok(OutputFormat::Sqlite),
+            "parquet" => Ok(OutputFormat::Parquet),
+            _ => Err(format!("unknown output format \"{}\" (available: {})", name, FORMATS.join(", "))),
+        }
+    }
+}
+
+/// The "output" section of settings.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputSettings {
+    pub layout: TableLayout,
+    /// Formats the word frequency, phrase and TF-IDF results are written in
+    pub formats: Vec<OutputFormat>,
+    /// Field delimiter of the CSV files: a single ASCII character such as "," ";" or "\t"
+    pub delimiter: String,
+    /// Start the CSV files with a UTF-8 byte order mark, so that Excel does not read them as ANSI
+    pub bom: bool,
+    /// Folder the results are written to. If empty, the "outputs" folder inside the input folder
+    pub directory: String,
+    /// Name of the output files, without the extension. See [`OutputPaths::file`] for the placeholders
+    pub file_name: String,
+    /// Write each run into its own subfolder of `directory`, named after the time the run started
+    pub run_subfolder: bool,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        OutputSettings {
+            layout: TableLayout::Wide,
+            formats: vec![OutputFormat::Csv],
+            delimiter: ",".to_string(),
+            bom: false,
+            directory: String::new(),
+            file_name: "{folder}_{analysis}".to_string(),
+            run_subfolder: false,
+        }
+    }
+}
+
+impl OutputSettings {
+    // check the values that serde cannot check by itself
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        match self.delimiter.as_bytes() {
+            [byte] if *byte != b'"' && *byte != b'\n' && *byte != b'\r' => {}
+            _ => return Err(format!("invalid CSV delimiter {:?} (expected a single ASCII character, other than a quote or a line break)", self.delimiter)),
+        }
+        // every file needs its own name
+        if !self.file_name.contains("{analysis}") {
+            return Err(format!("the file name template \"{}\" must contain {{analysis}}", self.file_name));
+        }
+        let mut rest = self.file_name.clone();
+        for placeholder in PLACEHOLDERS {
+            rest = rest.replace(placeholder, "");
+        }
+        if rest.contains(['{', '}', '/', '\\']) {
+            return Err(format!("invalid file name template \"{}\" (placeholders: {}, and no folder separators)", self.file_name, PLACEHOLDERS.join(" ")));
+        }
+        Ok(())
+    }
+
+    /// Whether the results are written in `format`
+    pub fn writes(&self, format: OutputFormat) -> bool {
+        self.formats.contains(&format)
+    }
+
+    /// Whether the wide CSV tables are written
+    pub fn wide_csv(&self) -> bool {
+        self.writes(OutputFormat::Csv) && self.layout.wide()
+    }
+
+    /// Whether the long CSV table is written
+    pub fn long_csv(&self) -> bool {
+        self.writes(OutputFormat::Csv) && self.layout.long()
+    }
+
+    /// The delimiter as a byte (a comma if it is not a single ASCII character)
+    pub fn delimiter_byte(&self) -> u8 {
+        match self.delimiter.as_bytes() {
+            [byte] => *byte,
+            _ => b',',
+        }
+    }
+}
+
+/// Where the results of a run go, and what they are named
+#[derive(Debug, Clone)]
+pub struct OutputPaths {
+    /// Name of the input folder, {folder} in the file names
+    pub folder_name: String,
+    /// Folder the files are written to (including the run subfolder, if any)
+    pub directory: PathBuf,
+    template: String,
+    timestamp: String,
+    settings_hash: String,
+}
+
+impl OutputPaths {
+    /// `directory` is the folder asked for (command line, settings, or default), the run subfolder is added to it if the settings ask for one
+    pub fn new(folder_name: &str, directory: &Path, settings: &Settings, started_at: chrono::DateTime<chrono::Local>) -> OutputPaths {
+        let output = &settings.output;
+        let directory = match output.run_subfolder {
+            true => directory.join(started_at.format("%Y-%m-%d_%H-%M-%S").to_string()),
+            false => directory.to_path_buf(),
+        };
+        OutputPaths {
+            folder_name: folder_name.to_string(),
+            directory,
+            template: output.file_name.clone(),
+            timestamp: started_at.format("%Y%m%d-%H%M%S").to_string(),
+            settings_hash: settings_hash(settings),
+        }
+    }
+
+    /// Path of an output file: the file name template with {folder} (input folder name), {analysis} (e.g. "wordFreq", "chart_zipf"),
+    /// {timestamp} (start of the run, YYYYMMDD-HHMMSS) and {settings_hash} (first 8 hex digits of the SHA-256 of the settings) replaced
+    pub fn file(&self, analysis: &str, extension: &str) -> PathBuf {
+        let name = self.template
+            .replace("{folder}", &self.folder_name)
+            .replace("{analysis}", analysis)
+            .replace("{timestamp}", &self.timestamp)
+            .replace("{settings_hash}", &self.settings_hash);
+        self.directory.join(format!("{}.{}", name, extension))
+    }
+}
+
+/// A document name as part of a file name: "/" and "\\" (files in subfolders) are percent-escaped, and so is "%" itself,
+/// so that two documents never share a file name (e.g. "a/b" becomes "a%2Fb", and "a_b" stays "a_b")
+pub fn file_name_part(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            '%' => result.push_str("%25"),
+            '/' => result.push_str("%2F"),
+            '\\' => result.push_str("%5C"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// First 8 hex digits of the SHA-256 of the settings, as JSON
+pub fn settings_hash(settings: &Settings) -> String {
+    let json = serde_json::to_string(settings).unwrap_or_default();
+    Sha256::digest(json.as_bytes()).iter().take(4).map(|byte| format!("{:02x}", byte)).collect()
+}
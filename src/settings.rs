@@ -0,0 +1,139 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::analyses::charts::ChartSettings;
+use crate::cache::CacheSettings;
+use crate::analyses::collocations::CollocationSettings;
+use crate::analyses::concordance::ConcordanceSettings;
+use crate::analyses::keyness::{self, KeynessSettings};
+use crate::analyses::lexical_diversity::LexicalDiversitySettings;
+use crate::analyses::ngrams::NgramSettings;
+use crate::analyses::wordcloud::WordCloudSettings;
+use crate::error::{Error, Result};
+use crate::normalizer::{self, NormalizationMode, NormalizationSettings};
+use crate::output::OutputSettings;
+use crate::tokenizer::TokenizerSettings;
+use crate::utils::{encoding, files};
+
+pub const DEFAULT_CHAPTER_SEPARATOR: &str = "<|eoc|>";
+
+/// Names of all the analyses that can be listed in `analyses`
+pub const ANALYSES: [&str; 12] = ["word_frequency", "tf_idf", "dispersion", "lexical_diversity", "zipf", "ngrams", "collocations", "keyness", "concordance", "charts", "wordcloud", "report"];
+
+/// Everything that can be configured in a settings.json file. Missing fields fall back to their defaults
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Target phrases to search for in the raw text (case-sensitive)
+    pub phrases: Vec<String>,
+    /// Separator used to split each file into chapters
+    pub chapter_separator: String,
+    /// Glob patterns (relative to the input folder) of the files to analyze
+    pub include: Vec<String>,
+    /// Glob patterns (relative to the input folder) of the files to skip, even if they match `include`
+    pub exclude: Vec<String>,
+    /// Encoding of the input files (e.g. "windows-1252", "utf-16le"), or "auto" to detect it for each file
+    pub encoding: String,
+    /// How the text is split into words
+    pub tokenizer: TokenizerSettings,
+    /// Optional stemming or lemmatization of the words
+    pub normalization: NormalizationSettings,
+    /// Analyses to run, see [`ANALYSES`]
+    pub analyses: Vec<String>,
+    pub lexical_diversity: LexicalDiversitySettings,
+    pub ngrams: NgramSettings,
+    pub collocations: CollocationSettings,
+    pub keyness: KeynessSettings,
+    /// Keyword-in-context search of the raw text
+    pub concordance: ConcordanceSettings,
+    pub charts: ChartSettings,
+    pub wordcloud: WordCloudSettings,
+    /// Layout and format of the output tables
+    pub output: OutputSettings,
+    /// Per-file cache of the word counts
+    pub cache: CacheSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            phrases: Vec::new(),
+            chapter_separator: DEFAULT_CHAPTER_SEPARATOR.to_string(),
+            include: vec!["**/*.txt".to_string()],
+            exclude: vec!["outputs/**".to_string()],
+            encoding: "auto".to_string(),
+            tokenizer: TokenizerSettings::default(),
+            normalization: NormalizationSettings::default(),
+            analyses: vec!["word_frequency".to_string(), "tf_idf".to_string()],
+            lexical_diversity: LexicalDiversitySettings::default(),
+            ngrams: NgramSettings::default(),
+            collocations: CollocationSettings::default(),
+            keyness: KeynessSettings::default(),
+            concordance: ConcordanceSettings::default(),
+            charts: ChartSettings::default(),
+            wordcloud: WordCloudSettings::default(),
+            output: OutputSettings::default(),
+            cache: CacheSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Read the settings from a json file, or use the defaults if the path is empty
+    pub fn load(json_path: &str) -> Result<Settings> {
+        // return the defaults if json_path is empty
+        if json_path.is_empty() {
+            return Ok(Settings::default());
+        }
+
+        let settings_error = |reason: String| Error::Settings { path: PathBuf::from(json_path), reason };
+        let settings_txt = fs::read_to_string(json_path).map_err(|source| settings_error(source.to_string()))?;
+        let mut settings: Settings = serde_json::from_str(&settings_txt).map_err(|error| settings_error(error.to_string()))?;
+        // if chapter_separator is empty, use default
+        if settings.chapter_separator.is_empty() {
+            settings.chapter_separator = DEFAULT_CHAPTER_SEPARATOR.to_string();
+        }
+        settings.validate().map_err(settings_error)?;
+
+        Ok(settings)
+    }
+
+    /// Whether `analysis` is listed in `analyses`
+    pub fn runs(&self, analysis: &str) -> bool {
+        self.analyses.iter().any(|name| name == analysis)
+    }
+
+    // check the values that serde cannot check by itself
+    fn validate(&self) -> std::result::Result<(), String> {
+        files::build_glob_set(&self.include)?;
+        files::build_glob_set(&self.exclude)?;
+        encoding::encoding_from_label(&self.encoding)?;
+        if self.normalization.mode == NormalizationMode::Stem {
+            normalizer::algorithm_from_language(&self.normalization.language)?;
+        }
+        if let Some(name) = self.analyses.iter().find(|name| !ANALYSES.contains(&name.as_str())) {
+            return Err(format!("unknown analysis \"{}\" (available: {})", name, ANALYSES.join(", ")));
+        }
+        if !(self.lexical_diversity.mtld_threshold > 0.0 && self.lexical_diversity.mtld_threshold < 1.0) {
+            return Err(format!("the MTLD threshold must be between 0 and 1, not {}", self.lexical_diversity.mtld_threshold));
+        }
+        if self.ngrams.min_n < 1 || self.ngrams.min_n > self.ngrams.max_n {
+            return Err(format!("invalid n-gram range {}..{}", self.ngrams.min_n, self.ngrams.max_n));
+        }
+        if self.collocations.window < 1 {
+            return Err("the collocation window must be at least 1".to_string());
+        }
+        keyness::critical_value(self.keyness.p_value)?;
+        if !self.keyness.reference.is_empty() && !Path::new(&self.keyness.reference).exists() {
+            return Err(format!("keyness reference \"{}\" does not exist", self.keyness.reference));
+        }
+        self.wordcloud.validate()?;
+        self.output.validate()?;
+        if !self.normalization.lemma_file.is_empty() && !Path::new(&self.normalization.lemma_file).is_file() {
+            return Err(format!("lemma file \"{}\" does not exist", self.normalization.lemma_file));
+        }
+        Ok(())
+    }
+}
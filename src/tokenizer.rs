@@ -0,0 +1,290 @@
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Splits the raw text of a chapter into words
+pub trait Tokenizer: Send + Sync {
+    fn tokenize(&self, text: &str) -> Vec<String>;
+}
+
+/// Which built-in tokenizer to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenizerKind {
+    /// Split on every character that is not alphanumeric, `-`, `*` or `’` (the original behaviour)
+    Legacy,
+    /// Unicode word boundaries (UAX #29)
+    Unicode,
+    /// [`ConfigurableTokenizer`], configured from the settings
+    Custom,
+}
+
+/// What to do with an apostrophe between two letters, as in `don't`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApostrophePolicy {
+    /// `don't` stays one word
+    Keep,
+    /// `don't` becomes `don` and `t`
+    Split,
+    /// `don't` becomes `dont`
+    Remove,
+}
+
+/// What to do with a hyphen between two letters, as in `TF-IDF`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HyphenPolicy {
+    /// `TF-IDF` stays one word
+    Keep,
+    /// `TF-IDF` becomes `TF` and `IDF`
+    Split,
+    /// `TF-IDF` becomes `TFIDF`
+    Remove,
+}
+
+/// The "tokenizer" section of settings.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TokenizerSettings {
+    pub kind: TokenizerKind,
+    /// Extra characters that are part of words (custom tokenizer only)
+    pub keep_characters: String,
+    pub apostrophes: ApostrophePolicy,
+    pub hyphens: HyphenPolicy,
+    /// Keep URLs such as `https://example.com/page` as a single word (custom tokenizer only)
+    pub keep_urls: bool,
+    /// Keep email addresses as a single word (custom tokenizer only)
+    pub keep_emails: bool,
+    /// Keep numbers such as `3.14` or `1,000` as a single word (custom tokenizer only)
+    pub keep_numbers: bool,
+}
+
+impl Default for TokenizerSettings {
+    fn default() -> Self {
+        TokenizerSettings {
+            kind: TokenizerKind::Legacy,
+            keep_characters: String::new(),
+            apostrophes: ApostrophePolicy::Keep,
+            hyphens: HyphenPolicy::Keep,
+            keep_urls: true,
+            keep_emails: true,
+            keep_numbers: true,
+        }
+    }
+}
+
+/// Build the tokenizer selected in the settings
+pub fn from_settings(settings: &TokenizerSettings) -> Box<dyn Tokenizer> {
+    match settings.kind {
+        TokenizerKind::Legacy => Box::new(LegacyTokenizer),
+        TokenizerKind::Unicode => Box::new(UnicodeTokenizer),
+        TokenizerKind::Custom => Box::new(ConfigurableTokenizer::from_settings(settings)),
+    }
+}
+
+
+/// The original word splitting: every character that is not alphanumeric, `-`, `*` or `’` is a separator
+pub struct LegacyTokenizer;
+
+fn trim_punctuations(text: &str) -> String {
+    // turn punctuations, except for "-" (and some other exceptions below) into whitespace, then replace multiple whitespaces with one
+    let mut result = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() || c.is_whitespace() || c == '-' || c == '*' || c == '’' { // add some other exceptions here
+            result.push(c);
+        } else {
+            result.push(' ');
+        }
+    }
+    result = result.split_whitespace().collect::<Vec<&str>>().join(" ");
+    result
+}
+
+fn split_into_words(text: &str) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        result.push(word.to_string());
+    }
+
+    result
+}
+
+impl Tokenizer for LegacyTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        split_into_words(&trim_punctuations(text))
+    }
+}
+
+
+/// Unicode word boundaries (UAX #29): keeps `don't`, `3.14` and `e.g` together, drops punctuation and whitespace
+pub struct UnicodeTokenizer;
+
+impl Tokenizer for UnicodeTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        text.unicode_words().map(|word| word.to_string()).collect()
+    }
+}
+
+
+/// Splits on whitespace and punctuation, with configurable exceptions for apostrophes, hyphens, extra characters, URLs, emails and numbers
+pub struct ConfigurableTokenizer {
+    pub keep_characters: Vec<char>,
+    pub apostrophes: ApostrophePolicy,
+    pub hyphens: HyphenPolicy,
+    pub keep_urls: bool,
+    pub keep_emails: bool,
+    pub keep_numbers: bool,
+}
+
+const APOSTROPHES: [char; 3] = ['\'', '’', 'ʼ'];
+const HYPHENS: [char; 3] = ['-', '‐', '‑'];
+
+// punctuation around a chunk that is never part of a URL, email or number, e.g. "(see https://example.com)."
+const CHUNK_TRIM: [char; 14] = ['(', ')', '[', ']', '{', '}', '<', '>', '"', '\'', ',', '.', ';', ':'];
+
+fn is_url(chunk: &str) -> bool {
+    let lowercase = chunk.to_lowercase();
+    (lowercase.starts_with("http://") || lowercase.starts_with("https://") || lowercase.starts_with("www."))
+        && chunk.len() > 8
+}
+
+fn is_email(chunk: &str) -> bool {
+    match chunk.split_once('@') {
+        Some((user, domain)) => {
+            !user.is_empty()
+                && user.chars().all(|c| c.is_alphanumeric() || "._%+-".contains(c))
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && domain.chars().all(|c| c.is_alphanumeric() || c == '.' || c == '-')
+        }
+        None => false,
+    }
+}
+
+// digits with "." or "," between them, e.g. 3.14, 1,000,000, 192.168.0.1
+fn is_number(chunk: &str) -> bool {
+    let chunk = chunk.strip_prefix(['-', '+']).unwrap_or(chunk);
+    chunk.chars().next().is_some_and(|c| c.is_ascii_digit())
+        && chunk.chars().last().is_some_and(|c| c.is_ascii_digit())
+        && chunk.chars().any(|c| c == '.' || c == ',')
+        && chunk.chars().all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+}
+
+impl ConfigurableTokenizer {
+    pub fn from_settings(settings: &TokenizerSettings) -> Self {
+        ConfigurableTokenizer {
+            keep_characters: settings.keep_characters.chars().collect(),
+            apostrophes: settings.apostrophes,
+            hyphens: settings.hyphens,
+            keep_urls: settings.keep_urls,
+            keep_emails: settings.keep_emails,
+            keep_numbers: settings.keep_numbers,
+        }
+    }
+
+    fn is_word_char(&self, c: char) -> bool {
+        c.is_alphanumeric() || self.keep_characters.contains(&c)
+    }
+
+    // split a whitespace-free chunk into words, applying the apostrophe and hyphen policies to the characters between two word characters
+    fn split_chunk(&self, chunk: &str, result: &mut Vec<String>) {
+        let chars: Vec<char> = chunk.chars().collect();
+        let mut word = String::new();
+        for (i, &c) in chars.iter().enumerate() {
+            if self.is_word_char(c) {
+                word.push(c);
+                continue;
+            }
+
+            // (keep the character in the word, drop it but keep the word whole)
+            let (keep, remove) = if APOSTROPHES.contains(&c) {
+                (self.apostrophes == ApostrophePolicy::Keep, self.apostrophes == ApostrophePolicy::Remove)
+            } else if HYPHENS.contains(&c) {
+                (self.hyphens == HyphenPolicy::Keep, self.hyphens == HyphenPolicy::Remove)
+            } else {
+                (false, false)
+            };
+            let between_words = !word.is_empty() && chars.get(i + 1).is_some_and(|next| self.is_word_char(*next));
+
+            if between_words && keep {
+                word.push(c);
+            } else if between_words && remove {
+                continue;
+            } else if !word.is_empty() {
+                result.push(std::mem::take(&mut word));
+            }
+        }
+        if !word.is_empty() {
+            result.push(word);
+        }
+    }
+}
+
+impl Tokenizer for ConfigurableTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+        for chunk in text.split_whitespace() {
+            let trimmed = chunk.trim_matches(|c: char| CHUNK_TRIM.contains(&c) || c == '!' || c == '?');
+            if (self.keep_urls && is_url(trimmed)) || (self.keep_emails && is_email(trimmed)) || (self.keep_numbers && is_number(trimmed)) {
+                result.push(trimmed.to_string());
+                continue;
+            }
+            self.split_chunk(chunk, &mut result);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom(apostrophes: ApostrophePolicy, hyphens: HyphenPolicy) -> ConfigurableTokenizer {
+        ConfigurableTokenizer::from_settings(&TokenizerSettings { kind: TokenizerKind::Custom, apostrophes, hyphens, ..TokenizerSettings::default() })
+    }
+
+    #[test]
+    fn legacy_keeps_hyphens_stars_and_curly_apostrophes() {
+        let words = LegacyTokenizer.tokenize("TF-IDF, don’t don't *sigh*... (3.14)");
+        assert_eq!(words, ["TF-IDF", "don’t", "don", "t", "*sigh*", "3", "14"]);
+    }
+
+    #[test]
+    fn unicode_word_boundaries() {
+        let words = UnicodeTokenizer.tokenize("Don't panic: 3.14 is π, isn't it?");
+        assert_eq!(words, ["Don't", "panic", "3.14", "is", "π", "isn't", "it"]);
+    }
+
+    #[test]
+    fn apostrophe_policies() {
+        assert_eq!(custom(ApostrophePolicy::Keep, HyphenPolicy::Keep).tokenize("don't 'quoted'"), ["don't", "quoted"]);
+        assert_eq!(custom(ApostrophePolicy::Split, HyphenPolicy::Keep).tokenize("don’t"), ["don", "t"]);
+        assert_eq!(custom(ApostrophePolicy::Remove, HyphenPolicy::Keep).tokenize("don't"), ["dont"]);
+    }
+
+    #[test]
+    fn hyphen_policies() {
+        assert_eq!(custom(ApostrophePolicy::Keep, HyphenPolicy::Keep).tokenize("TF-IDF - well-"), ["TF-IDF", "well"]);
+        assert_eq!(custom(ApostrophePolicy::Keep, HyphenPolicy::Split).tokenize("TF-IDF"), ["TF", "IDF"]);
+        assert_eq!(custom(ApostrophePolicy::Keep, HyphenPolicy::Remove).tokenize("TF-IDF"), ["TFIDF"]);
+    }
+
+    #[test]
+    fn urls_emails_and_numbers() {
+        let tokenizer = custom(ApostrophePolicy::Keep, HyphenPolicy::Keep);
+        let words = tokenizer.tokenize("(see https://example.com/a-b.) Mail me@example.org, pay 1,000.50!");
+        assert_eq!(words, ["see", "https://example.com/a-b", "Mail", "me@example.org", "pay", "1,000.50"]);
+
+        let tokenizer = ConfigurableTokenizer { keep_urls: false, keep_emails: false, keep_numbers: false, ..tokenizer };
+        let words = tokenizer.tokenize("https://example.com me@example.org 3.14");
+        assert_eq!(words, ["https", "example", "com", "me", "example", "org", "3", "14"]);
+    }
+
+    #[test]
+    fn keep_characters() {
+        let tokenizer = ConfigurableTokenizer { keep_characters: vec!['_', '#'], ..custom(ApostrophePolicy::Keep, HyphenPolicy::Keep) };
+        assert_eq!(tokenizer.tokenize("snake_case #tag"), ["snake_case", "#tag"]);
+    }
+}
@@ -0,0 +1,181 @@
+// Apache Parquet files of the results, in long layout with typed columns (text, integers, floats)
+
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use parquet::basic::{Compression, LogicalType, Repetition, Type as PhysicalType};
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int32Type, Int64Type};
+use parquet::errors::Result;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type;
+
+use crate::corpus::{FrequencyTable, TfIdfTable};
+
+/// The values of a column, all of the same length as the other columns of the table
+pub enum Column {
+    Text(Vec<String>),
+    Int32(Vec<i32>),
+    Int64(Vec<i64>),
+    /// None is written as null (values that could not be calculated)
+    Double(Vec<Option<f64>>),
+}
+
+fn field(name: &str, column: &Column) -> Result<Arc<Type>> {
+    let (physical, logical, repetition) = match column {
+        Column::Text(_) => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String), Repetition::REQUIRED),
+        Column::Int32(_) => (PhysicalType::INT32, None, Repetition::REQUIRED),
+        Column::Int64(_) => (PhysicalType::INT64, None, Repetition::REQUIRED),
+        Column::Double(_) => (PhysicalType::DOUBLE, None, Repetition::OPTIONAL),
+    };
+    Ok(Arc::new(Type::primitive_type_builder(name, physical).with_logical_type(logical).with_repetition(repetition).build()?))
+}
+
+/// Write the (name, values) columns to a Snappy-compressed Parquet file, as a single row group
+pub fn write_table(path: &Path, columns: &[(&str, Column)]) -> Result<()> {
+    let fields = columns.iter().map(|(name, column)| field(name, column)).collect::<Result<Vec<_>>>()?;
+    let schema = Arc::new(Type::group_type_builder("schema").with_fields(fields).build()?);
+    let properties = Arc::new(WriterProperties::builder().set_compression(Compression::SNAPPY).build());
+
+    let file = File::create(path)?;
+    let mut writer = SerializedFileWriter::new(file, schema, properties)?;
+    let mut row_group = writer.next_row_group()?;
+    for (_, column) in columns {
+        let Some(mut column_writer) = row_group.next_column()? else { break };
+        match column {
+            Column::Text(values) => {
+                let values: Vec<ByteArray> = values.iter().map(|value| ByteArray::from(value.as_str())).collect();
+                column_writer.typed::<ByteArrayType>().write_batch(&values, None, None)?;
+            }
+            Column::Int32(values) => {
+                column_writer.typed::<Int32Type>().write_batch(values, None, None)?;
+            }
+            Column::Int64(values) => {
+                column_writer.typed::<Int64Type>().write_batch(values, None, None)?;
+            }
+            Column::Double(values) => {
+                // definition level 1 for a value, 0 for a null
+                let levels: Vec<i16> = values.iter().map(|value| value.is_some() as i16).collect();
+                let present: Vec<f64> = values.iter().flatten().copied().collect();
+                column_writer.typed::<DoubleType>().write_batch(&present, Some(&levels), None)?;
+            }
+        }
+        column_writer.close()?;
+    }
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+/// file, chapter, term, count, relative_frequency: one row per word of each chapter (target phrases excluded)
+pub fn frequency_columns(frequencies: &FrequencyTable, phrases: &[String]) -> Vec<(&'static str, Column)> {
+    let (mut files, mut chapters, mut terms, mut counts, mut relative) = (Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for file_name in frequencies.file_names() {
+        for (i, chapter) in frequencies.files[&file_name].iter().enumerate() {
+            let mut words: Vec<(&String, &usize)> = chapter.iter().filter(|(term, count)| **count > 0 && !phrases.contains(term)).collect();
+            words.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+            let total: usize = words.iter().map(|(_, count)| **count).sum();
+            for (term, count) in words {
+                files.push(file_name.clone());
+                chapters.push((i + 1) as i32);
+                terms.push(term.clone());
+                counts.push(*count as i64);
+                relative.push(Some(*count as f64 / total as f64));
+            }
+        }
+    }
+    vec![
+        ("file", Column::Text(files)),
+        ("chapter", Column::Int32(chapters)),
+        ("term", Column::Text(terms)),
+        ("count", Column::Int64(counts)),
+        ("relative_frequency", Column::Double(relative)),
+    ]
+}
+
+/// file, chapter, phrase, count: one row per target phrase of each chapter (including the ones not found)
+pub fn phrase_columns(frequencies: &FrequencyTable, phrases: &[String]) -> Vec<(&'static str, Column)> {
+    let (mut files, mut chapters, mut phrase_names, mut counts) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for file_name in frequencies.file_names() {
+        for (i, chapter) in frequencies.files[&file_name].iter().enumerate() {
+            for phrase in phrases {
+                files.push(file_name.clone());
+                chapters.push((i + 1) as i32);
+                phrase_names.push(phrase.clone());
+                counts.push(chapter.get(phrase).copied().unwrap_or(0) as i64);
+            }
+        }
+    }
+    vec![
+        ("file", Column::Text(files)),
+        ("chapter", Column::Int32(chapters)),
+        ("phrase", Column::Text(phrase_names)),
+        ("count", Column::Int64(counts)),
+    ]
+}
+
+/// file, chapter, term, tfidf: one row per term of each chapter of the TF-IDF table, highest first
+pub fn tf_idf_columns(tfidf: &TfIdfTable) -> Vec<(&'static str, Column)> {
+    let (mut files, mut chapters, mut terms, mut values) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for file_name in tfidf.file_names() {
+        for (i, chapter) in tfidf.files[&file_name].iter().enumerate() {
+            let mut rows: Vec<(&String, &f64)> = chapter.iter().collect();
+            rows.sort_by(|a, b| b.1.partial_cmp(a.1).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.0.cmp(b.0)));
+            for (term, value) in rows {
+                files.push(file_name.clone());
+                chapters.push((i + 1) as i32);
+                terms.push(term.clone());
+                values.push(Some(*value).filter(|value| value.is_finite()));
+            }
+        }
+    }
+    vec![
+        ("file", Column::Text(files)),
+        ("chapter", Column::Int32(chapters)),
+        ("term", Column::Text(terms)),
+        ("tfidf", Column::Double(values)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use crate::analyses::test_util::two_documents;
+
+    #[test]
+    fn column_types() {
+        let (_, frequencies, tfidf, phrases) = two_documents();
+        let path = std::env::temp_dir().join(format!("columnar_types_{}.parquet", std::process::id()));
+        let read_back = |columns: &[(&str, Column)]| {
+            write_table(&path, columns).unwrap();
+            let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+            let metadata = reader.metadata().file_metadata();
+            let types: Vec<(String, PhysicalType, Option<LogicalType>)> = metadata.schema_descr().columns()
+                .iter()
+                .map(|column| (column.name().to_string(), column.physical_type(), column.logical_type_ref().cloned()))
+                .collect();
+            (metadata.num_rows(), types)
+        };
+
+        let (rows, types) = read_back(&frequency_columns(&frequencies, &phrases));
+        // 4 + 2 + 4 words in the chapters
+        assert_eq!(rows, 10);
+        assert_eq!(types, [
+            ("file".to_string(), PhysicalType::BYTE_ARRAY, Some(LogicalType::String)),
+            ("chapter".to_string(), PhysicalType::INT32, None),
+            ("term".to_string(), PhysicalType::BYTE_ARRAY, Some(LogicalType::String)),
+            ("count".to_string(), PhysicalType::INT64, None),
+            ("relative_frequency".to_string(), PhysicalType::DOUBLE, None),
+        ]);
+
+        let (rows, types) = read_back(&phrase_columns(&frequencies, &phrases));
+        assert_eq!((rows, types[3].1), (3, PhysicalType::INT64));
+        let (rows, types) = read_back(&tf_idf_columns(&tfidf));
+        assert!(rows > 0);
+        assert_eq!(types[3], ("tfidf".to_string(), PhysicalType::DOUBLE, None));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
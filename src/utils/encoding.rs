@@ -0,0 +1,122 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use chardetng::{EncodingDetector, Iso2022JpDetection, Utf8Detection};
+
+// look up an encoding by its WHATWG label (e.g. "utf-8", "latin1", "windows-1252", "utf-16le")
+// "auto" (or an empty string) means detect the encoding for each file
+pub fn encoding_from_label(label: &str) -> Result<Option<&'static Encoding>, String> {
+    let label = label.trim();
+    if label.is_empty() || label.eq_ignore_ascii_case("auto") {
+        return Ok(None);
+    }
+    match Encoding::for_label(label.as_bytes()) {
+        Some(encoding) => Ok(Some(encoding)),
+        None => Err(format!("unknown encoding \"{}\"", label)),
+    }
+}
+
+// UTF-16 text without a BOM: most characters of latin text have a zero byte on one side
+fn sniff_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(4096) & !1];
+    if sample.len() < 4 {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let zeros_even = sample.iter().step_by(2).filter(|byte| **byte == 0).count();
+    let zeros_odd = sample.iter().skip(1).step_by(2).filter(|byte| **byte == 0).count();
+
+    // more than 40% of the pairs have a zero byte on the same side, and almost none on the other side
+    if zeros_odd * 10 > pairs * 4 && zeros_even * 10 < pairs {
+        Some(UTF_16LE)
+    } else if zeros_even * 10 > pairs * 4 && zeros_odd * 10 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+fn detect(bytes: &[u8]) -> &'static Encoding {
+    // before the UTF-8 check: ASCII text in UTF-16 is also valid UTF-8 (full of NUL characters)
+    if let Some(encoding) = sniff_utf16(bytes) {
+        return encoding;
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+    let mut detector = EncodingDetector::new(Iso2022JpDetection::Deny);
+    detector.feed(bytes, true);
+    detector.guess(None, Utf8Detection::Allow)
+}
+
+// decode the raw bytes of a file into a UTF-8 String, returns the text and the name of the encoding used
+// order: byte order mark > forced encoding (from settings) > UTF-16 sniffing > valid UTF-8 > charset detection
+// malformed sequences are an error with a forced encoding, and are replaced with U+FFFD when the encoding was detected
+pub fn decode(bytes: &[u8], forced: Option<&'static Encoding>) -> Result<(String, &'static str), String> {
+    let (encoding, bom_length) = match Encoding::for_bom(bytes) {
+        Some((encoding, bom_length)) => (encoding, bom_length),
+        None => (forced.unwrap_or_else(|| detect(bytes)), 0),
+    };
+
+    let (text, had_errors) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+    if had_errors && forced.is_some() && bom_length == 0 {
+        return Err(format!("the file is not valid {}", encoding.name()));
+    }
+
+    Ok((text.into_owned(), encoding.name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(encoding_from_label("auto"), Ok(None));
+        assert_eq!(encoding_from_label(""), Ok(None));
+        assert_eq!(encoding_from_label(" latin1 ").unwrap().unwrap().name(), "windows-1252");
+        assert!(encoding_from_label("klingon").is_err());
+    }
+
+    #[test]
+    fn valid_utf8_is_kept() {
+        assert_eq!(decode("déjà vu".as_bytes(), None), Ok(("déjà vu".to_string(), "UTF-8")));
+    }
+
+    #[test]
+    fn bom_wins() {
+        let mut bytes = vec![0xEF, 0xBB, 0xBF];
+        bytes.extend_from_slice("café".as_bytes());
+        assert_eq!(decode(&bytes, None), Ok(("café".to_string(), "UTF-8")));
+
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(utf16le("café"));
+        // the BOM is trusted even over a forced encoding
+        assert_eq!(decode(&bytes, Some(encoding_rs::WINDOWS_1252)), Ok(("café".to_string(), "UTF-16LE")));
+    }
+
+    #[test]
+    fn utf16_without_bom_is_sniffed() {
+        let text = "The quick brown fox jumps over the lazy dog";
+        assert_eq!(decode(&utf16le(text), None), Ok((text.to_string(), "UTF-16LE")));
+
+        let big_endian: Vec<u8> = text.encode_utf16().flat_map(|unit| unit.to_be_bytes()).collect();
+        assert_eq!(decode(&big_endian, None), Ok((text.to_string(), "UTF-16BE")));
+    }
+
+    #[test]
+    fn legacy_single_byte_is_detected() {
+        let text = "Le cœur a ses raisons que la raison ne connaît point. Élève, père, mère, où, déjà, été.";
+        let (bytes, _, _) = encoding_rs::WINDOWS_1252.encode(text);
+        assert_eq!(decode(&bytes, None), Ok((text.to_string(), "windows-1252")));
+    }
+
+    #[test]
+    fn forced_encoding_must_match() {
+        // 0xE9 alone is not valid UTF-8
+        assert!(decode(&[b'c', b'a', b'f', 0xE9], Some(UTF_8)).is_err());
+        assert_eq!(decode(&[b'c', b'a', b'f', 0xE9], Some(encoding_rs::WINDOWS_1252)), Ok(("café".to_string(), "windows-1252")));
+    }
+}
@@ -0,0 +1,78 @@
+use std::path::{Path, PathBuf};
+use std::fs;
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+use crate::error::{Error, Result};
+
+// returns the reason as a String so that the caller can attach the settings file path
+pub fn build_glob_set(patterns: &[String]) -> std::result::Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        // "*" should not cross folders, so that "*.txt" only matches the top level and "**/*.txt" matches everything
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .case_insensitive(true)
+            .build()
+            .map_err(|error| format!("invalid glob pattern \"{}\": {}", pattern, error))?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|error| error.to_string())
+}
+
+// path relative to the root folder, with "/" as separator on every platform
+pub fn relative_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+fn walk(root: &Path, dir: &Path, include: &GlobSet, exclude: &GlobSet, result: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        // the input folder itself must be readable, but unreadable subfolders are only skipped
+        Err(source) if dir == root => return Err(Error::Io { path: dir.to_path_buf(), source }),
+        Err(source) => {
+            let error = Error::Io { path: dir.to_path_buf(), source };
+            println!("\x1b[33m  WARNING: Skipping folder. {}\x1b[0m", error);
+            return Ok(());
+        }
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let relative = relative_path(root, &path);
+        // file_type does not follow symlinks: links to folders are skipped, so that a link to a parent folder cannot loop forever
+        let Ok(file_type) = entry.file_type() else { continue };
+        if file_type.is_symlink() && path.is_dir() {
+            continue;
+        }
+        if file_type.is_dir() {
+            // no need to look inside a folder that is excluded as a whole
+            if exclude.is_match(&relative) {
+                continue;
+            }
+            walk(root, &path, include, exclude, result)?;
+        } else if path.is_file() && include.is_match(&relative) && !exclude.is_match(&relative) {
+            result.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+// recursively list all files in folder matching any of the include patterns and none of the exclude patterns
+pub fn discover_files(folder_dir: &Path, include: &[String], exclude: &[String]) -> Result<Vec<PathBuf>> {
+    let settings_error = |reason: String| Error::Settings { path: PathBuf::new(), reason };
+    let include = build_glob_set(include).map_err(settings_error)?;
+    let exclude = build_glob_set(exclude).map_err(settings_error)?;
+
+    let mut result: Vec<PathBuf> = Vec::new();
+    walk(folder_dir, folder_dir, &include, &exclude, &mut result)?;
+    result.sort();
+
+    Ok(result)
+}
@@ -0,0 +1,175 @@
+// Self-contained SVG charts: each function returns the whole SVG document as a string, like tables.rs does for CSV
+
+use std::fmt::Write;
+
+pub const PALETTE: [&str; 10] = ["#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7", "#9c755f", "#bab0ac"];
+
+const FONT: &str = "font-family=\"Helvetica, Arial, sans-serif\"";
+
+/// A line of a plot: name (for the legend), color and (x, y) points
+pub struct Series {
+    pub name: String,
+    pub color: String,
+    pub points: Vec<(f64, f64)>,
+    pub dashed: bool,
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn svg_start(width: f64, height: f64, title: &str) -> String {
+    let mut svg = String::new();
+    let _ = writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">", w = width, h = height);
+    let _ = writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>");
+    let _ = writeln!(svg, "<text x=\"{}\" y=\"24\" {} font-size=\"16\" font-weight=\"bold\" text-anchor=\"middle\">{}</text>", width / 2.0, FONT, escape(title));
+    svg
+}
+
+// shortest decimal form of a value, for axis and bar labels
+fn format_value(value: f64) -> String {
+    if value == value.round() && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    }
+    else if value.abs() >= 0.01 {
+        format!("{:.2}", value)
+    }
+    else {
+        format!("{:.2e}", value)
+    }
+}
+
+/// Horizontal bar chart of (label, value), drawn in the given order (top to bottom)
+pub fn bar_chart(title: &str, bars: &[(String, f64)], color: &str) -> String {
+    let (width, label_width, bar_height, top) = (800.0, 160.0, 22.0, 44.0);
+    let height = top + bars.len() as f64 * bar_height + 20.0;
+    let max_value = bars.iter().map(|(_, value)| *value).fold(0.0, f64::max);
+    let plot_width = width - label_width - 80.0;
+
+    let mut svg = svg_start(width, height, title);
+    for (i, (label, value)) in bars.iter().enumerate() {
+        let y = top + i as f64 * bar_height;
+        let bar_width = if max_value > 0.0 { value / max_value * plot_width } else { 0.0 };
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" {} font-size=\"12\" text-anchor=\"end\">{}</text>", label_width - 8.0, y + bar_height * 0.65, FONT, escape(label));
+        let _ = writeln!(svg, "<rect x=\"{}\" y=\"{}\" width=\"{:.2}\" height=\"{}\" fill=\"{}\"><title>{}: {}</title></rect>", label_width, y + 2.0, bar_width, bar_height - 4.0, color, escape(label), format_value(*value));
+        let _ = writeln!(svg, "<text x=\"{:.2}\" y=\"{}\" {} font-size=\"11\" fill=\"#555\">{}</text>", label_width + bar_width + 6.0, y + bar_height * 0.65, FONT, format_value(*value));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Line plot with logarithmic axes. Points with x or y <= 0 are skipped
+pub fn log_log_plot(title: &str, x_label: &str, y_label: &str, series: &[Series]) -> String {
+    let (width, height) = (800.0, 600.0);
+    let (left, right, top, bottom) = (70.0, 180.0, 44.0, 56.0);
+    let (plot_width, plot_height) = (width - left - right, height - top - bottom);
+
+    let logs: Vec<(f64, f64)> = series
+        .iter()
+        .flat_map(|series| &series.points)
+        .filter(|(x, y)| *x > 0.0 && *y > 0.0)
+        .map(|(x, y)| (x.log10(), y.log10()))
+        .collect();
+    // axes span whole powers of 10
+    let min_x = logs.iter().map(|(x, _)| *x).fold(f64::INFINITY, f64::min).floor().min(0.0);
+    let max_x = logs.iter().map(|(x, _)| *x).fold(f64::NEG_INFINITY, f64::max).ceil().max(min_x + 1.0);
+    let min_y = logs.iter().map(|(_, y)| *y).fold(f64::INFINITY, f64::min).floor().min(0.0);
+    let max_y = logs.iter().map(|(_, y)| *y).fold(f64::NEG_INFINITY, f64::max).ceil().max(min_y + 1.0);
+    let to_x = |x: f64| left + (x - min_x) / (max_x - min_x) * plot_width;
+    let to_y = |y: f64| top + plot_height - (y - min_y) / (max_y - min_y) * plot_height;
+
+    let mut svg = svg_start(width, height, title);
+    // grid and ticks
+    for power in (min_x as i32)..=(max_x as i32) {
+        let x = to_x(power as f64);
+        let _ = writeln!(svg, "<line x1=\"{x:.2}\" y1=\"{}\" x2=\"{x:.2}\" y2=\"{}\" stroke=\"#ddd\"/>", top, top + plot_height);
+        let _ = writeln!(svg, "<text x=\"{x:.2}\" y=\"{}\" {} font-size=\"11\" text-anchor=\"middle\">{}</text>", top + plot_height + 16.0, FONT, format_value(10f64.powi(power)));
+    }
+    for power in (min_y as i32)..=(max_y as i32) {
+        let y = to_y(power as f64);
+        let _ = writeln!(svg, "<line x1=\"{}\" y1=\"{y:.2}\" x2=\"{}\" y2=\"{y:.2}\" stroke=\"#ddd\"/>", left, left + plot_width);
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"{:.2}\" {} font-size=\"11\" text-anchor=\"end\">{}</text>", left - 6.0, y + 4.0, FONT, format_value(10f64.powi(power)));
+    }
+    let _ = writeln!(svg, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"#333\"/>", left, top, plot_width, plot_height);
+    let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" {} font-size=\"13\" text-anchor=\"middle\">{}</text>", left + plot_width / 2.0, height - 14.0, FONT, escape(x_label));
+    let _ = writeln!(svg, "<text x=\"18\" y=\"{y}\" {} font-size=\"13\" text-anchor=\"middle\" transform=\"rotate(-90 18 {y})\">{}</text>", FONT, escape(y_label), y = top + plot_height / 2.0);
+
+    // lines and legend
+    for (i, series) in series.iter().enumerate() {
+        let points: Vec<String> = series.points
+            .iter()
+            .filter(|(x, y)| *x > 0.0 && *y > 0.0)
+            .map(|(x, y)| format!("{:.2},{:.2}", to_x(x.log10()), to_y(y.log10())))
+            .collect();
+        let dash = if series.dashed { " stroke-dasharray=\"6 4\"" } else { "" };
+        let _ = writeln!(svg, "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\"{}><title>{}</title></polyline>", points.join(" "), series.color, dash, escape(&series.name));
+
+        let y = top + 10.0 + i as f64 * 18.0;
+        let x = left + plot_width + 14.0;
+        let _ = writeln!(svg, "<line x1=\"{}\" y1=\"{y}\" x2=\"{}\" y2=\"{y}\" stroke=\"{}\" stroke-width=\"2\"{}/>", x, x + 20.0, series.color, dash);
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" {} font-size=\"11\">{}</text>", x + 26.0, y + 4.0, FONT, escape(&series.name));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Heatmap of values[row][column], from white (0) to `color` (the largest value)
+pub fn heatmap(title: &str, rows: &[String], columns: &[String], values: &[Vec<f64>], color: (u8, u8, u8)) -> String {
+    let (cell, label_width, top) = (18.0, 180.0, 130.0);
+    let width = label_width + columns.len() as f64 * cell + 20.0;
+    let height = top + rows.len() as f64 * cell + 20.0;
+    let max_value = values.iter().flatten().copied().fold(0.0, f64::max);
+    let shade = |value: f64| {
+        let t = if max_value > 0.0 { (value / max_value).clamp(0.0, 1.0) } else { 0.0 };
+        let mix = |channel: u8| (255.0 - (255.0 - channel as f64) * t).round() as u8;
+        format!("#{:02x}{:02x}{:02x}", mix(color.0), mix(color.1), mix(color.2))
+    };
+
+    let mut svg = svg_start(width.max(400.0), height, title);
+    for (j, column) in columns.iter().enumerate() {
+        let x = label_width + j as f64 * cell + cell / 2.0;
+        let _ = writeln!(svg, "<text x=\"{x:.2}\" y=\"{y}\" {} font-size=\"11\" transform=\"rotate(-60 {x:.2} {y})\">{}</text>", FONT, escape(column), y = top - 6.0);
+    }
+    for (i, row) in rows.iter().enumerate() {
+        let y = top + i as f64 * cell;
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" {} font-size=\"11\" text-anchor=\"end\">{}</text>", label_width - 6.0, y + cell * 0.7, FONT, escape(row));
+        for (j, column) in columns.iter().enumerate() {
+            let value = values.get(i).and_then(|row| row.get(j)).copied().unwrap_or(0.0);
+            let _ = writeln!(svg, "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"#eee\"><title>{} / {}: {}</title></rect>", label_width + j as f64 * cell, y, cell, cell, shade(value), escape(row), escape(column), format_value(value));
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(points: Vec<(f64, f64)>) -> Series {
+        Series { name: "a".to_string(), color: "#000".to_string(), points, dashed: false }
+    }
+
+    #[test]
+    fn escape_markup() {
+        assert_eq!(escape("<a href=\"x\">R&D</a>"), "&lt;a href=&quot;x&quot;&gt;R&amp;D&lt;/a&gt;");
+    }
+
+    #[test]
+    fn bar_chart_of_zeros() {
+        let svg = bar_chart("Zeros", &[("a".to_string(), 0.0), ("b".to_string(), 0.0)], "#000");
+        assert!(!svg.contains("NaN"));
+        assert_eq!(svg.matches("width=\"0.00\"").count(), 2);
+    }
+
+    #[test]
+    fn log_log_plot_skips_non_positive_points() {
+        let svg = log_log_plot("Plot", "x", "y", &[series(vec![(0.0, 5.0), (1.0, 10.0), (10.0, -1.0), (100.0, 1.0)])]);
+        // (1, 10) and (100, 1) on axes from 10^0 to 10^2 and 10^0 to 10^1
+        assert!(svg.contains("<polyline points=\"70.00,44.00 620.00,544.00\""));
+
+        let svg = log_log_plot("Plot", "x", "y", &[series(vec![(0.0, 1.0), (1.0, 0.0)])]);
+        assert!(svg.contains("<polyline points=\"\""));
+        assert!(!svg.contains("NaN") && !svg.contains("inf"));
+    }
+}
@@ -0,0 +1,183 @@
+// JSON and JSON Lines documents of the results: each function returns the whole document as a string, like tables.rs does for CSV
+
+use std::path::PathBuf;
+use std::collections::HashMap;
+use serde_json::{json, Map, Value};
+
+use crate::corpus::{Corpus, FrequencyTable, TfIdfTable};
+use crate::settings::Settings;
+
+/// What the metadata block needs to know about the run itself
+pub struct RunInfo {
+    pub input_folder: PathBuf,
+    pub started_at: chrono::DateTime<chrono::Local>,
+}
+
+/// Tool version, timestamps, input folder, settings and token counts of each file and chapter
+pub fn metadata(corpus: &Corpus, frequencies: &FrequencyTable, settings: &Settings, run: &RunInfo) -> Value {
+    let files: Vec<Value> = corpus.documents
+        .iter()
+        .map(|document| {
+            let chapter_tokens: Vec<usize> = document.chapters.iter().map(|chapter| chapter.tokens).collect();
+            json!({
+                "name": document.name,
+                "path": document.path.display().to_string(),
+                "encoding": document.encoding,
+                "chapters": document.chapters.len(),
+                "tokens": chapter_tokens.iter().sum::<usize>(),
+                "chapter_tokens": chapter_tokens,
+            })
+        })
+        .collect();
+    let tokens: usize = corpus.documents.iter().flat_map(|document| &document.chapters).map(|chapter| chapter.tokens).sum();
+
+    json!({
+        "tool": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "started_at": run.started_at.to_rfc3339(),
+        "exported_at": chrono::Local::now().to_rfc3339(),
+        "input_folder": run.input_folder.display().to_string(),
+        "documents": frequencies.document_count(),
+        "tokens": tokens,
+        "types": corpus.normalized_vocabulary.len(),
+        "files": files,
+        "settings": settings,
+    })
+}
+
+// one chapter: its number (from 1), word count, and the word, phrase and TF-IDF maps (terms in alphabetical order)
+fn chapter(number: usize, tokens: usize, counts: &HashMap<String, usize>, tfidf: Option<&HashMap<String, f64>>, phrases: &[String]) -> Map<String, Value> {
+    let mut words = Map::new();
+    let mut phrase_counts = Map::new();
+    for (term, count) in counts {
+        match phrases.contains(term) {
+            true => phrase_counts.insert(term.clone(), json!(count)),
+            false => words.insert(term.clone(), json!(count)),
+        };
+    }
+    let mut result = Map::new();
+    result.insert("chapter".to_string(), json!(number));
+    result.insert("tokens".to_string(), json!(tokens));
+    result.insert("words".to_string(), Value::Object(words));
+    result.insert("phrases".to_string(), Value::Object(phrase_counts));
+    if let Some(tfidf) = tfidf {
+        // NaN and infinite values are not valid JSON, they become null
+        let tfidf: Map<String, Value> = tfidf.iter().map(|(term, value)| (term.clone(), json!(value))).collect();
+        result.insert("tfidf".to_string(), Value::Object(tfidf));
+    }
+    result
+}
+
+// (file name, chapters) in file name order
+fn files(corpus: &Corpus, frequencies: &FrequencyTable, tfidf: &TfIdfTable, phrases: &[String]) -> Vec<(String, Vec<Map<String, Value>>)> {
+    frequencies.file_names()
+        .into_iter()
+        .map(|file_name| {
+            let document = corpus.document(&file_name);
+            let chapters = frequencies.files[&file_name]
+                .iter()
+                .enumerate()
+                .map(|(i, counts)| {
+                    let tokens = document.and_then(|document| document.chapters.get(i)).map(|chapter| chapter.tokens).unwrap_or(0);
+                    let tfidf = tfidf.files.get(&file_name).and_then(|chapters| chapters.get(i));
+                    chapter(i + 1, tokens, counts, tfidf, phrases)
+                })
+                .collect();
+            (file_name, chapters)
+        })
+        .collect()
+}
+
+/// The whole results as one JSON document: { "metadata": ..., "files": { file: { "chapters": [ { "words", "phrases", "tfidf" } ] } } }
+pub fn results_to_json_string(metadata: Value, corpus: &Corpus, frequencies: &FrequencyTable, tfidf: &TfIdfTable, phrases: &[String]) -> String {
+    println!("\nGenerating a JSON string...");
+    let start = std::time::Instant::now();
+
+    let files: Map<String, Value> = files(corpus, frequencies, tfidf, phrases)
+        .into_iter()
+        .map(|(file_name, chapters)| (file_name, json!({ "chapters": chapters })))
+        .collect();
+    let result = serde_json::to_string_pretty(&json!({ "metadata": metadata, "files": files })).unwrap_or_default();
+
+    let duration = start.elapsed();
+    println!("\x1b[2m  Generating json string in {} ms\x1b[0m", duration.as_millis());
+
+    result
+}
+
+/// JSON Lines: a { "type": "metadata" } line, then one { "type": "chapter", "file": ... } line per chapter
+pub fn results_to_ndjson_string(metadata: Value, corpus: &Corpus, frequencies: &FrequencyTable, tfidf: &TfIdfTable, phrases: &[String]) -> String {
+    println!("\nGenerating a JSON Lines string...");
+    let start = std::time::Instant::now();
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut header = Map::new();
+    header.insert("type".to_string(), json!("metadata"));
+    if let Value::Object(metadata) = metadata {
+        header.extend(metadata);
+    }
+    lines.push(Value::Object(header).to_string());
+    for (file_name, chapters) in files(corpus, frequencies, tfidf, phrases) {
+        for chapter in chapters {
+            let mut line = Map::new();
+            line.insert("type".to_string(), json!("chapter"));
+            line.insert("file".to_string(), json!(file_name));
+            line.extend(chapter);
+            lines.push(Value::Object(line).to_string());
+        }
+    }
+    let mut result = lines.join("\n");
+    result.push('\n');
+
+    let duration = start.elapsed();
+    println!("\x1b[2m  Generating json lines string in {} ms\x1b[0m", duration.as_millis());
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyses::test_util::two_documents;
+
+    fn metadata_of(corpus: &Corpus, frequencies: &FrequencyTable) -> Value {
+        let run = RunInfo { input_folder: PathBuf::from("books"), started_at: chrono::Local::now() };
+        metadata(corpus, frequencies, &Settings::default(), &run)
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let (corpus, frequencies, tfidf, phrases) = two_documents();
+        let json = results_to_json_string(metadata_of(&corpus, &frequencies), &corpus, &frequencies, &tfidf, &phrases);
+        let parsed: Value = serde_json::from_str(&json).unwrap();
+
+        let metadata = &parsed["metadata"];
+        assert_eq!(metadata["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!((metadata["documents"].as_u64(), metadata["tokens"].as_u64()), (Some(3), Some(11)));
+        assert_eq!(metadata["files"][0]["chapter_tokens"], json!([4, 2]));
+
+        let a = &parsed["files"]["a.txt"]["chapters"];
+        assert_eq!(a.as_array().unwrap().len(), 2);
+        assert_eq!((a[0]["chapter"].as_u64(), a[0]["tokens"].as_u64()), (Some(1), Some(4)));
+        assert_eq!(a[0]["words"]["dragon"], 1);
+        assert_eq!(a[0]["phrases"], json!({ "red dragon": 1 }));
+        assert_eq!(a[1]["words"]["gold"], 1);
+        assert_eq!(parsed["files"]["b.txt"]["chapters"][0]["words"]["the"], 2);
+        assert!(a[0]["tfidf"]["sleeps"].as_f64().unwrap() > 0.0);
+    }
+
+    #[test]
+    fn ndjson_round_trip() {
+        let (corpus, frequencies, tfidf, phrases) = two_documents();
+        let ndjson = results_to_ndjson_string(metadata_of(&corpus, &frequencies), &corpus, &frequencies, &tfidf, &phrases);
+        let lines: Vec<Value> = ndjson.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!((&lines[0]["type"], &lines[0]["version"]), (&json!("metadata"), &json!(env!("CARGO_PKG_VERSION"))));
+        let chapters: Vec<(&Value, &Value)> = lines[1..].iter().map(|line| (&line["file"], &line["chapter"])).collect();
+        assert_eq!(chapters, [(&json!("a.txt"), &json!(1)), (&json!("a.txt"), &json!(2)), (&json!("b.txt"), &json!(1))]);
+        assert!(lines[1..].iter().all(|line| line["type"] == "chapter"));
+        assert_eq!(lines[2]["words"], json!({ "red": 1, "gold": 1 }));
+        assert_eq!(lines[3]["words"]["knight"], 1);
+    }
+}
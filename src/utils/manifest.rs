@@ -0,0 +1,129 @@
+// manifest.json of a run: which inputs and settings produced the output files, and how long each stage took
+
+use std::time::Duration;
+use serde_json::{json, Value};
+
+use crate::corpus::Corpus;
+use crate::error::Result;
+use crate::output::{self, OutputPaths};
+use crate::settings::Settings;
+use crate::utils::{json, utils};
+
+/// Time taken by each stage of a run, in the order they ran
+#[derive(Debug, Default)]
+pub struct Timings {
+    stages: Vec<(String, Duration)>,
+}
+
+impl Timings {
+    /// Run `stage` and record how long it took
+    pub fn time<T>(&mut self, name: &str, stage: impl FnOnce() -> T) -> T {
+        let start = std::time::Instant::now();
+        let result = stage();
+        self.stages.push((name.to_string(), start.elapsed()));
+        result
+    }
+
+    fn to_json(&self) -> Vec<Value> {
+        self.stages
+            .iter()
+            .map(|(name, duration)| json!({ "stage": name, "ms": (duration.as_secs_f64() * 1_000_000.0).round() / 1000.0 }))
+            .collect()
+    }
+}
+
+// path, size and SHA-256 of each input file (of the bytes that were analyzed, the files are not read again), and its token counts
+fn input_files(corpus: &Corpus) -> Vec<Value> {
+    corpus.documents
+        .iter()
+        .map(|document| {
+            let chapter_tokens: Vec<usize> = document.chapters.iter().map(|chapter| chapter.tokens).collect();
+            json!({
+                "name": document.name,
+                "path": document.path.display().to_string(),
+                "size": document.size,
+                "sha256": document.sha256,
+                "encoding": document.encoding,
+                "chapters": document.chapters.len(),
+                "tokens": chapter_tokens.iter().sum::<usize>(),
+                "chapter_tokens": chapter_tokens,
+            })
+        })
+        .collect()
+}
+
+/// Tool version, effective settings, input files with their hashes, token counts and stage timings
+pub fn manifest(paths: &OutputPaths, corpus: &Corpus, settings: &Settings, run: &json::RunInfo, timings: &Timings) -> Value {
+    let tokens: usize = corpus.documents.iter().flat_map(|document| &document.chapters).map(|chapter| chapter.tokens).sum();
+
+    json!({
+        "tool": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "started_at": run.started_at.to_rfc3339(),
+        "finished_at": chrono::Local::now().to_rfc3339(),
+        "input_folder": run.input_folder.display().to_string(),
+        "output_folder": paths.directory.display().to_string(),
+        "settings_hash": output::settings_hash(settings),
+        "chapter_separator": settings.chapter_separator,
+        "stopwords_sha256": utils::stopwords_hash(),
+        "tokens": tokens,
+        "types": corpus.normalized_vocabulary.len(),
+        "files": input_files(corpus),
+        "stages": timings.to_json(),
+        "settings": settings,
+    })
+}
+
+/// Write the manifest next to the other output files, always as "manifest.json" so that it can be found without knowing the file name template
+pub fn write_manifest(paths: &OutputPaths, corpus: &Corpus, settings: &Settings, run: &json::RunInfo, timings: &Timings) -> Result<()> {
+    println!("------------------------------------------------------------");
+    println!("WRITING MANIFEST...");
+    let start = std::time::Instant::now();
+
+    let manifest = manifest(paths, corpus, settings, run, timings);
+    let output_file_path = paths.directory.join("manifest.json");
+    utils::create_output_folder(&paths.directory)?;
+    utils::write_output_file(&output_file_path, serde_json::to_string_pretty(&manifest).unwrap_or_default().as_bytes())?;
+
+    let duration = start.elapsed();
+    println!("Manifest completed in {} ms", duration.as_millis());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+    use crate::corpus::{Chapter, Document};
+
+    #[test]
+    fn inputs_and_settings() {
+        let chapters = vec![Chapter { tokens: 3, ..Chapter::default() }, Chapter { tokens: 2, ..Chapter::default() }];
+        let document = Document {
+            name: "a.txt".to_string(),
+            path: PathBuf::from("books/a.txt"),
+            encoding: "UTF-8".to_string(),
+            size: 27,
+            sha256: utils::sha256_hex(b"abc"),
+            chapters,
+        };
+        let corpus = Corpus { documents: vec![document], ..Corpus::default() };
+        let settings = Settings { chapter_separator: "CHAPTER".to_string(), ..Settings::default() };
+        let run = json::RunInfo { input_folder: PathBuf::from("books"), started_at: chrono::Local::now() };
+        let paths = OutputPaths::new("books", Path::new("books/outputs"), &settings, run.started_at);
+
+        let manifest = manifest(&paths, &corpus, &settings, &run, &Timings::default());
+        assert_eq!(manifest["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(manifest["settings"], serde_json::to_value(&settings).unwrap());
+        assert_eq!(manifest["chapter_separator"], "CHAPTER");
+        assert_eq!(manifest["stopwords_sha256"], utils::stopwords_hash());
+        assert_eq!(manifest["tokens"], 5);
+
+        let file = &manifest["files"][0];
+        assert_eq!(file["path"], PathBuf::from("books/a.txt").display().to_string());
+        assert_eq!(file["size"], 27);
+        assert_eq!(file["sha256"], "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(file["chapter_tokens"], json!([3, 2]));
+    }
+}
@@ -0,0 +1,191 @@
+// SQLite database of the results, with one normalized table per kind of data
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use rusqlite::{params, Connection};
+
+use crate::corpus::{Corpus, FrequencyTable, TfIdfTable};
+use crate::error::{Error, Result};
+use crate::utils::utils;
+
+const SCHEMA: &str = "
+CREATE TABLE metadata (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+CREATE TABLE documents (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    path TEXT NOT NULL,
+    encoding TEXT NOT NULL,
+    tokens INTEGER NOT NULL
+);
+CREATE TABLE chapters (
+    id INTEGER PRIMARY KEY,
+    document_id INTEGER NOT NULL REFERENCES documents(id),
+    number INTEGER NOT NULL,
+    tokens INTEGER NOT NULL,
+    UNIQUE (document_id, number)
+);
+CREATE TABLE terms (
+    id INTEGER PRIMARY KEY,
+    term TEXT NOT NULL UNIQUE,
+    is_stopword INTEGER NOT NULL
+);
+CREATE TABLE counts (
+    chapter_id INTEGER NOT NULL REFERENCES chapters(id),
+    term_id INTEGER NOT NULL REFERENCES terms(id),
+    count INTEGER NOT NULL,
+    PRIMARY KEY (chapter_id, term_id)
+) WITHOUT ROWID;
+CREATE TABLE phrase_counts (
+    chapter_id INTEGER NOT NULL REFERENCES chapters(id),
+    phrase TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    PRIMARY KEY (chapter_id, phrase)
+) WITHOUT ROWID;
+CREATE TABLE tfidf (
+    chapter_id INTEGER NOT NULL REFERENCES chapters(id),
+    term_id INTEGER NOT NULL REFERENCES terms(id),
+    tfidf REAL NOT NULL,
+    PRIMARY KEY (chapter_id, term_id)
+) WITHOUT ROWID;
+";
+
+// created after the data is inserted, which is faster than keeping them up to date row by row
+const INDEXES: &str = "
+CREATE INDEX chapters_document ON chapters (document_id);
+CREATE INDEX counts_term ON counts (term_id);
+CREATE INDEX phrase_counts_phrase ON phrase_counts (phrase);
+CREATE INDEX tfidf_term ON tfidf (term_id);
+";
+
+/// Write the documents, chapters, terms, word and phrase counts and TF-IDF (if any) to a new database at `path`.
+/// `metadata` are (key, value) pairs such as the tool version and the settings
+pub fn write_database(path: &Path, corpus: &Corpus, frequencies: &FrequencyTable, tfidf: &TfIdfTable, phrases: &[String], metadata: &[(String, String)]) -> Result<()> {
+    println!("\nWriting a SQLite database...");
+    let start = std::time::Instant::now();
+
+    // the database is built next to `path` and renamed once complete,
+    // so that a failed run leaves neither a half-written database nor an empty one in place of the previous results
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    // start from an empty database (e.g. not the leftover of an interrupted run)
+    let _ = fs::remove_file(&temporary);
+
+    if let Err(error) = fill_database(&temporary, corpus, frequencies, tfidf, phrases, metadata) {
+        let _ = fs::remove_file(&temporary);
+        return Err(Error::Output { path: path.to_path_buf(), source: std::io::Error::other(error) });
+    }
+    fs::rename(&temporary, path).map_err(|source| Error::Output { path: path.to_path_buf(), source })?;
+
+    let duration = start.elapsed();
+    println!("\x1b[2m  Writing sqlite database in {} ms\x1b[0m", duration.as_millis());
+
+    Ok(())
+}
+
+// the connection is closed when this returns, before the file is renamed
+fn fill_database(path: &Path, corpus: &Corpus, frequencies: &FrequencyTable, tfidf: &TfIdfTable, phrases: &[String], metadata: &[(String, String)]) -> rusqlite::Result<()> {
+    let mut connection = Connection::open(path)?;
+    connection.execute_batch("PRAGMA journal_mode = OFF; PRAGMA synchronous = OFF;")?;
+    connection.execute_batch(SCHEMA)?;
+
+    let transaction = connection.transaction()?;
+    {
+        let mut insert_metadata = transaction.prepare("INSERT INTO metadata (key, value) VALUES (?1, ?2)")?;
+        for (key, value) in metadata {
+            insert_metadata.execute(params![key, value])?;
+        }
+
+        let mut insert_document = transaction.prepare("INSERT INTO documents (name, path, encoding, tokens) VALUES (?1, ?2, ?3, ?4)")?;
+        let mut insert_chapter = transaction.prepare("INSERT INTO chapters (document_id, number, tokens) VALUES (?1, ?2, ?3)")?;
+        let mut insert_term = transaction.prepare("INSERT INTO terms (term, is_stopword) VALUES (?1, ?2)")?;
+        let mut insert_count = transaction.prepare("INSERT INTO counts (chapter_id, term_id, count) VALUES (?1, ?2, ?3)")?;
+        let mut insert_phrase = transaction.prepare("INSERT INTO phrase_counts (chapter_id, phrase, count) VALUES (?1, ?2, ?3)")?;
+        let mut insert_tfidf = transaction.prepare("INSERT INTO tfidf (chapter_id, term_id, tfidf) VALUES (?1, ?2, ?3)")?;
+
+        // <term, id>
+        let mut term_ids: HashMap<String, i64> = HashMap::new();
+        let mut term_id = |term: &str, insert_term: &mut rusqlite::Statement| -> rusqlite::Result<i64> {
+            if let Some(id) = term_ids.get(term) {
+                return Ok(*id);
+            }
+            insert_term.execute(params![term, utils::is_stopword(term)])?;
+            let id = transaction.last_insert_rowid();
+            term_ids.insert(term.to_string(), id);
+            Ok(id)
+        };
+
+        for file_name in frequencies.file_names() {
+            let document = corpus.document(&file_name);
+            let chapter_tokens = |i: usize| document.and_then(|document| document.chapters.get(i)).map(|chapter| chapter.tokens).unwrap_or(0);
+            let chapters = &frequencies.files[&file_name];
+            let tokens: usize = (0..chapters.len()).map(chapter_tokens).sum();
+            insert_document.execute(params![
+                file_name,
+                document.map(|document| document.path.display().to_string()).unwrap_or_default(),
+                document.map(|document| document.encoding.clone()).unwrap_or_default(),
+                tokens as i64,
+            ])?;
+            let document_id = transaction.last_insert_rowid();
+
+            for (i, counts) in chapters.iter().enumerate() {
+                insert_chapter.execute(params![document_id, (i + 1) as i64, chapter_tokens(i) as i64])?;
+                let chapter_id = transaction.last_insert_rowid();
+                for (term, count) in counts {
+                    if phrases.contains(term) {
+                        insert_phrase.execute(params![chapter_id, term, *count as i64])?;
+                    }
+                    else if *count > 0 {
+                        let id = term_id(term, &mut insert_term)?;
+                        insert_count.execute(params![chapter_id, id, *count as i64])?;
+                    }
+                }
+                if let Some(values) = tfidf.files.get(&file_name).and_then(|chapters| chapters.get(i)) {
+                    for (term, value) in values.iter().filter(|(term, value)| value.is_finite() && !phrases.contains(term)) {
+                        let id = term_id(term, &mut insert_term)?;
+                        insert_tfidf.execute(params![chapter_id, id, value])?;
+                    }
+                }
+            }
+        }
+    }
+    transaction.commit()?;
+    connection.execute_batch(INDEXES)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyses::test_util::two_documents;
+
+    #[test]
+    fn row_counts() {
+        let (corpus, frequencies, tfidf, phrases) = two_documents();
+        let path = std::env::temp_dir().join(format!("sqlite_row_counts_{}.sqlite", std::process::id()));
+        write_database(&path, &corpus, &frequencies, &tfidf, &phrases, &[("version".to_string(), "test".to_string())]).unwrap();
+
+        let connection = Connection::open(&path).unwrap();
+        let rows = |table: &str| connection.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get::<_, i64>(0)).unwrap();
+        assert_eq!(rows("documents"), 2);
+        assert_eq!(rows("chapters"), 3);
+        // the, red, dragon, sleeps, gold, knight, fights
+        assert_eq!(rows("terms"), 7);
+        // 4 + 2 + 4 words in the chapters
+        assert_eq!(rows("counts"), 10);
+        assert_eq!(rows("phrase_counts"), 3);
+        // the words other than stop words: 3 + 2 + 3 (the target phrase is only in phrase_counts)
+        assert_eq!(rows("tfidf"), 8);
+
+        let the: (i64, i64) = connection
+            .query_row("SELECT SUM(count), is_stopword FROM counts JOIN terms ON terms.id = term_id WHERE term = 'the'", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!(the, (3, 1));
+
+        drop(connection);
+        fs::remove_file(&path).unwrap();
+        assert!(!Path::new(&format!("{}.tmp", path.display())).exists());
+    }
+}
@@ -1,6 +1,46 @@
 use std::collections::{HashMap, HashSet};
 
-pub fn combined_file_map_to_csv_string_usize(file_names: &Vec<String>, words_list: &HashSet<String>, data: &HashMap<String, HashMap<String, usize>>, phrases: &Vec<String>) -> String {
+use crate::analyses::collocations::{Collocation, CollocationTable};
+use crate::analyses::concordance::ConcordanceLine;
+use crate::analyses::dispersion::Dispersion;
+use crate::analyses::keyness::Keyword;
+use crate::analyses::lexical_diversity::LexicalDiversity;
+use crate::analyses::zipf::ZipfFit;
+use crate::corpus::{FrequencyTable, TfIdfTable};
+use crate::error::Result;
+use crate::output::OutputSettings;
+
+// all tables go through the csv crate: fields with the delimiter, a quote or a line break are quoted (RFC 4180)
+fn csv_writer(output: &OutputSettings) -> csv::Writer<Vec<u8>> {
+    csv::WriterBuilder::new()
+        .delimiter(output.delimiter_byte())
+        .terminator(csv::Terminator::Any(b'\n'))
+        .from_writer(Vec::new())
+}
+
+// the whole table as a string, starting with a UTF-8 byte order mark if asked for (Excel needs it to read UTF-8)
+fn finish(writer: csv::Writer<Vec<u8>>, output: &OutputSettings) -> Result<String> {
+    let bytes = writer.into_inner().map_err(|error| csv::Error::from(error.into_error()))?;
+    let table = String::from_utf8(bytes).map_err(|error| csv::Error::from(std::io::Error::new(std::io::ErrorKind::InvalidData, error)))?;
+    if output.bom {
+        Ok(format!("\u{feff}{}", table))
+    }
+    else {
+        Ok(table)
+    }
+}
+
+// values that could not be calculated (NaN, infinite) are left empty
+fn float_field(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    }
+    else {
+        String::new()
+    }
+}
+
+pub fn combined_file_map_to_csv_string_usize(file_names: &[String], words_list: &HashSet<String>, data: &HashMap<String, HashMap<String, usize>>, phrases: &[String], output: &OutputSettings) -> Result<String> {
     // target is a csv file with the following format
     // headers: word, file1, file2, file3, file4, file5..., total
     // each row is a word, and the value of that word in each file, and the total value accross all files
@@ -13,9 +53,9 @@ pub fn combined_file_map_to_csv_string_usize(file_names: &Vec<String>, words_lis
     let mut total_value: HashMap<String, usize> = HashMap::new();
     for word in words_list {
         let mut value = 0;
-        for (_, word_value) in data {
-            if word_value.contains_key(word) {
-                value += word_value.get(word).unwrap();
+        for word_value in data.values() {
+            if let Some(word_value) = word_value.get(word) {
+                value += word_value;
             }
         }
         total_value.insert(word.clone(), value);
@@ -31,7 +71,7 @@ pub fn combined_file_map_to_csv_string_usize(file_names: &Vec<String>, words_lis
         if phrases.contains(word) {
             target_phrases.push(word.to_string());
         }
-        else if word.chars().next().unwrap().is_uppercase() {
+        else if word.chars().next().is_some_and(char::is_uppercase) {
             capitalized_words.push(word.to_string());
         }
         else if word.chars().all(char::is_numeric) {
@@ -64,37 +104,33 @@ pub fn combined_file_map_to_csv_string_usize(file_names: &Vec<String>, words_lis
 
     // Create the csv string
     let start = std::time::Instant::now();
+    let mut writer = csv_writer(output);
     // create the headers
-    let mut result = String::from("Words");
-    for file_name in file_names {
-        result.push_str(&format!(",{}", file_name));
-    }
-    result.push_str(",Total\n");
+    let mut header: Vec<String> = vec!["Words".to_string()];
+    header.extend(file_names.iter().cloned());
+    header.push("Total".to_string());
+    writer.write_record(&header)?;
 
     // create the rows
     for word in &sorted_words {
-        result.push_str(&format!("{}", word));
+        let mut row: Vec<String> = vec![word.clone()];
         let mut total = 0;
         for file_name in file_names {
-            if data.get(file_name).unwrap().contains_key(word) {
-                let value = data.get(file_name).unwrap().get(word).unwrap();
-                result.push_str(&format!(",{}", value));
-                total += value;
-            }
-            else {
-                result.push_str(",0");
-            }
+            let value = data.get(file_name).unwrap().get(word).copied().unwrap_or(0);
+            row.push(value.to_string());
+            total += value;
         }
-        result.push_str(&format!(",{}\n", total));
+        row.push(total.to_string());
+        writer.write_record(&row)?;
     }
     let duration = start.elapsed();
     println!("\x1b[2m  Generating csv string in {} ms\x1b[0m", duration.as_millis());
 
-    result
+    finish(writer, output)
 }
 
 // similar to above but for the full size data and f64: HashMap<String, Vec<HashMap<String, f64>>> (includes chapters)
-pub fn tf_idf_combined_file_map_to_csv_string_f64_fullsize(file_names: &Vec<String>, words_list: &HashSet<String>, data: &HashMap<String, Vec<HashMap<String, f64>>>, phrases: &Vec<String>) -> String {
+pub fn tf_idf_combined_file_map_to_csv_string_f64_fullsize(file_names: &[String], words_list: &HashSet<String>, data: &HashMap<String, Vec<HashMap<String, f64>>>, phrases: &[String], output: &OutputSettings) -> Result<String> {
     // Example:
     // Word, Book 1 _ #1, Book 1 _ #2, Book 1 Total, Book 2 _ #1, Book 2 _ #2, Book 2 _ #3, Book 2 Total, Corpus Total
     // coder, 1, 1, 2, 4, 2, 3, 9, 11
@@ -106,10 +142,10 @@ pub fn tf_idf_combined_file_map_to_csv_string_f64_fullsize(file_names: &Vec<Stri
     let mut total_value: HashMap<String, f64> = HashMap::new();
     for word in words_list {
         let mut value = 0.0;
-        for (_, chapters) in data {
+        for chapters in data.values() {
             for chapter in chapters {
-                if chapter.contains_key(word) {
-                    value += chapter.get(word).unwrap();
+                if let Some(chapter_value) = chapter.get(word) {
+                    value += chapter_value;
                 }
             }
         }
@@ -126,7 +162,7 @@ pub fn tf_idf_combined_file_map_to_csv_string_f64_fullsize(file_names: &Vec<Stri
         if phrases.contains(word) {
             target_phrases.push(word.to_string());
         }
-        else if word.chars().next().unwrap().is_uppercase() {
+        else if word.chars().next().is_some_and(char::is_uppercase) {
             capitalized_words.push(word.to_string());
         }
         else if word.chars().all(char::is_numeric) {
@@ -159,84 +195,322 @@ pub fn tf_idf_combined_file_map_to_csv_string_f64_fullsize(file_names: &Vec<Stri
     // Check if the data has chapters --> affect how we generate the csv string
     // If there is no chapters, then the headers will simply be "Words", then the file names then "Corpus Total"
     // If there are chapters, then the headers will be something like Words, Book 1 _ #1, Book 1 _ #2, Book 1 Total, Book 2 _ #1, Book 2 _ #2, Book 2 _ #3, Book 2 Total, Corpus Total
-    let mut has_chapters = false;
-    for (_, chapters) in data {
-        if chapters.len() > 1 {
-            has_chapters = true;
-            break;
-        }
-    }
+    let has_chapters = data.values().any(|chapters| chapters.len() > 1);
 
     // Create the csv string
     let start = std::time::Instant::now();
+    let mut writer = csv_writer(output);
     if has_chapters {
         // create the headers
-        let mut result = String::from("Words");
+        let mut header: Vec<String> = vec!["Words".to_string()];
         for file_name in file_names {
             let chapters = data.get(file_name).unwrap().len();
             for i in 0..chapters {
-                result.push_str(&format!(",{} _ #{}", file_name, i + 1));
+                header.push(format!("{} _ #{}", file_name, i + 1));
             }
-            result.push_str(&format!(",{}_Total", file_name));
+            header.push(format!("{}_Total", file_name));
         }
-        result.push_str(",Corpus Total\n");
+        header.push("Corpus Total".to_string());
+        writer.write_record(&header)?;
 
         // create the rows
         for word in &sorted_words {
-            result.push_str(&format!("{}", word));
+            let mut row: Vec<String> = vec![word.clone()];
             let mut corpus_total = 0.0;
             for file_name in file_names {
-                let chapters = data.get(file_name).unwrap().len();
                 let mut file_total = 0.0;
-                for i in 0..chapters {
-                    if data.get(file_name).unwrap()[i].contains_key(word) {
-                        let value = data.get(file_name).unwrap()[i].get(word).unwrap();
-                        result.push_str(&format!(",{}", value));
-                        file_total += value;
-                    }
-                    else {
-                        result.push_str(",0");
-                    }
+                for chapter in data.get(file_name).unwrap() {
+                    let value = chapter.get(word).copied().unwrap_or(0.0);
+                    row.push(value.to_string());
+                    file_total += value;
                 }
-                result.push_str(&format!(",{}", file_total));
+                row.push(file_total.to_string());
                 corpus_total += file_total;
             }
-
-            result.push_str(&format!(",{}\n", corpus_total));
+            row.push(corpus_total.to_string());
+            writer.write_record(&row)?;
         }
-        let duration = start.elapsed();
-        println!("\x1b[2m  Generating csv string in {} ms\x1b[0m", duration.as_millis());
-
-        return result;
     }
     // No chapters!
     else {
         // create the headers
-        let mut result = String::from("Words");
-        for file_name in file_names {
-            result.push_str(&format!(",{}", file_name));
-        }
-        result.push_str(",Corpus Total\n");
+        let mut header: Vec<String> = vec!["Words".to_string()];
+        header.extend(file_names.iter().cloned());
+        header.push("Corpus Total".to_string());
+        writer.write_record(&header)?;
 
         // create the rows
         for word in &sorted_words {
-            result.push_str(&format!("{}", word));
+            let mut row: Vec<String> = vec![word.clone()];
             let mut corpus_total = 0.0;
             for file_name in file_names {
-                if data.get(file_name).unwrap()[0].contains_key(word) { // Vec length is 1 if there is no chapters
-                    let value = data.get(file_name).unwrap()[0].get(word).unwrap();
-                    result.push_str(&format!(",{}", value));
-                    corpus_total += value;
-                }
-                else {
-                    result.push_str(",0");
-                }
+                // Vec length is 1 if there is no chapters
+                let value = data.get(file_name).unwrap()[0].get(word).copied().unwrap_or(0.0);
+                row.push(value.to_string());
+                corpus_total += value;
+            }
+            row.push(corpus_total.to_string());
+            writer.write_record(&row)?;
+        }
+    }
+    let duration = start.elapsed();
+    println!("\x1b[2m  Generating csv string in {} ms\x1b[0m", duration.as_millis());
+
+    finish(writer, output)
+}
+
+// long (tidy) layout: one row per file, chapter and term, sorted by file, chapter, then most frequent term first
+// relative_frequency is the count over the number of words of the chapter (target phrases are not words)
+// tfidf is left empty for the terms that are not in the TF-IDF table (stopwords, or TF-IDF not calculated)
+pub fn long_table_to_csv_string(frequencies: &FrequencyTable, tfidf: &TfIdfTable, phrases: &[String], output: &OutputSettings) -> Result<String> {
+    // Example:
+    // file, chapter, term, count, relative_frequency, tfidf
+    // Book 1, 1, ring, 12, 0.0031, 0.0012
+
+    println!("\nGenerating a CSV string...");
+    let start = std::time::Instant::now();
+
+    let mut writer = csv_writer(output);
+    writer.write_record(["file", "chapter", "term", "count", "relative_frequency", "tfidf"])?;
+    for file_name in frequencies.file_names() {
+        for (i, chapter) in frequencies.files[&file_name].iter().enumerate() {
+            let words: usize = chapter.iter().filter(|(term, _)| !phrases.contains(term)).map(|(_, count)| count).sum();
+            let tfidf_chapter = tfidf.files.get(&file_name).and_then(|chapters| chapters.get(i));
+            // zero counts (target phrases not found in the chapter) are left out
+            let mut terms: Vec<(&String, &usize)> = chapter.iter().filter(|(_, count)| **count > 0).collect();
+            terms.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+            for (term, count) in terms {
+                let relative_frequency = if words > 0 { *count as f64 / words as f64 } else { f64::NAN };
+                let tfidf_value = tfidf_chapter.and_then(|chapter| chapter.get(term)).copied().unwrap_or(f64::NAN);
+                writer.write_record([
+                    file_name.clone(), (i + 1).to_string(), term.clone(), count.to_string(), float_field(relative_frequency), float_field(tfidf_value),
+                ])?;
             }
-            result.push_str(&format!(",{}\n", corpus_total));
         }
-        let duration = start.elapsed();
-        println!("\x1b[2m  Generating csv string in {} ms\x1b[0m", duration.as_millis());
+    }
+
+    let duration = start.elapsed();
+    println!("\x1b[2m  Generating csv string in {} ms\x1b[0m", duration.as_millis());
+
+    finish(writer, output)
+}
+
+// ranked collocations: the corpus rows first, then the rows of each file (sorted by file name)
+pub fn collocations_to_csv_string(collocations: &CollocationTable, output: &OutputSettings) -> Result<String> {
+    // Example:
+    // Scope, Rank, Word 1, Word 2, Pair Frequency, Word 1 Frequency, Word 2 Frequency, PMI, Log-Likelihood, T-Score, Dice
+    // Corpus Total, 1, gold, ring, 3, 3, 3, 8.2, 24.1, 1.7, 1
+
+    println!("\nGenerating a CSV string...");
+    let start = std::time::Instant::now();
+
+    let write_rows = |writer: &mut csv::Writer<Vec<u8>>, scope: &str, rows: &[Collocation]| -> Result<()> {
+        for (i, row) in rows.iter().enumerate() {
+            writer.write_record([
+                scope.to_string(), (i + 1).to_string(), row.first.clone(), row.second.clone(),
+                row.frequency.to_string(), row.first_frequency.to_string(), row.second_frequency.to_string(),
+                row.pmi.to_string(), row.log_likelihood.to_string(), row.t_score.to_string(), row.dice.to_string(),
+            ])?;
+        }
+        Ok(())
+    };
 
-        return result;
+    let mut writer = csv_writer(output);
+    writer.write_record(["Scope", "Rank", "Word 1", "Word 2", "Pair Frequency", "Word 1 Frequency", "Word 2 Frequency", "PMI", "Log-Likelihood", "T-Score", "Dice"])?;
+    write_rows(&mut writer, "Corpus Total", &collocations.corpus)?;
+    let mut file_names: Vec<&String> = collocations.files.keys().collect();
+    file_names.sort();
+    for file_name in file_names {
+        write_rows(&mut writer, file_name, &collocations.files[file_name])?;
     }
-}
\ No newline at end of file
+
+    let duration = start.elapsed();
+    println!("\x1b[2m  Generating csv string in {} ms\x1b[0m", duration.as_millis());
+
+    finish(writer, output)
+}
+
+// keywords, in the given order (strongest first)
+pub fn keywords_to_csv_string(keywords: &[Keyword], output: &OutputSettings) -> Result<String> {
+    // Example:
+    // Words, Target Frequency, Reference Frequency, Target Per Million, Reference Per Million, Log-Likelihood, Chi-Squared, %DIFF, Log Ratio
+    // ring, 44, 2, 1520.3, 12.1, 250.4, 301.2, 12464.5, 6.97
+
+    println!("\nGenerating a CSV string...");
+    let start = std::time::Instant::now();
+
+    let mut writer = csv_writer(output);
+    writer.write_record(["Words", "Target Frequency", "Reference Frequency", "Target Per Million", "Reference Per Million", "Log-Likelihood", "Chi-Squared", "%DIFF", "Log Ratio"])?;
+    for keyword in keywords {
+        writer.write_record([
+            keyword.word.clone(), keyword.target_frequency.to_string(), keyword.reference_frequency.to_string(),
+            keyword.target_per_million.to_string(), keyword.reference_per_million.to_string(),
+            keyword.log_likelihood.to_string(), keyword.chi_squared.to_string(), keyword.percent_diff.to_string(), keyword.log_ratio.to_string(),
+        ])?;
+    }
+
+    let duration = start.elapsed();
+    println!("\x1b[2m  Generating csv string in {} ms\x1b[0m", duration.as_millis());
+
+    finish(writer, output)
+}
+
+// concordance lines, in the given order
+pub fn concordance_to_csv_string(lines: &[ConcordanceLine], output: &OutputSettings) -> Result<String> {
+    // Example:
+    // Query, File, Chapter, Offset, Left, Match, Right
+    // gold ring, book1, 2, 1534, "the old man took the", gold ring, "from his pocket, and"
+
+    println!("\nGenerating a CSV string...");
+    let start = std::time::Instant::now();
+
+    let mut writer = csv_writer(output);
+    writer.write_record(["Query", "File", "Chapter", "Offset", "Left", "Match", "Right"])?;
+    for line in lines {
+        writer.write_record([
+            line.query.clone(), line.file.clone(), line.chapter.to_string(), line.offset.to_string(),
+            line.left.clone(), line.matched.clone(), line.right.clone(),
+        ])?;
+    }
+
+    let duration = start.elapsed();
+    println!("\x1b[2m  Generating csv string in {} ms\x1b[0m", duration.as_millis());
+
+    finish(writer, output)
+}
+
+// dispersion of each word, in the given order (most frequent first)
+pub fn dispersion_to_csv_string(dispersions: &[Dispersion], output: &OutputSettings) -> Result<String> {
+    // Example:
+    // Words, Total, Range, Juilland's D, Gries' DP, CV
+    // ring, 44, 3, 0.62, 0.35, 1.15
+
+    println!("\nGenerating a CSV string...");
+    let start = std::time::Instant::now();
+
+    let mut writer = csv_writer(output);
+    writer.write_record(["Words", "Total", "Range", "Juilland's D", "Gries' DP", "CV"])?;
+    for dispersion in dispersions {
+        writer.write_record([
+            dispersion.word.clone(), dispersion.total.to_string(), dispersion.range.to_string(),
+            dispersion.juilland_d.to_string(), dispersion.dp.to_string(), dispersion.cv.to_string(),
+        ])?;
+    }
+
+    let duration = start.elapsed();
+    println!("\x1b[2m  Generating csv string in {} ms\x1b[0m", duration.as_millis());
+
+    finish(writer, output)
+}
+
+// one row per chapter, file (if it has chapters) and for the corpus, in the given order
+pub fn lexical_diversity_to_csv_string(rows: &[LexicalDiversity], output: &OutputSettings) -> Result<String> {
+    // Example:
+    // File, Chapter, Tokens, Types, TTR, Root TTR, Hapax Legomena, Dis Legomena, Yule's K, MTLD, HD-D
+    // book1, 1, 5230, 1204, 0.23, 16.65, 702, 180, 98.1, 84.2, 0.83
+    // book1, Total, ...
+
+    println!("\nGenerating a CSV string...");
+    let start = std::time::Instant::now();
+
+    let mut writer = csv_writer(output);
+    writer.write_record(["File", "Chapter", "Tokens", "Types", "TTR", "Root TTR", "Hapax Legomena", "Dis Legomena", "Yule's K", "MTLD", "HD-D"])?;
+    for row in rows {
+        let chapter = row.chapter.map(|chapter| chapter.to_string()).unwrap_or_else(|| "Total".to_string());
+        // HD-D is left empty for the units shorter than its sample size
+        let hd_d = row.hd_d.map(|hd_d| hd_d.to_string()).unwrap_or_default();
+        writer.write_record([
+            row.file.clone(), chapter, row.tokens.to_string(), row.types.to_string(), row.ttr.to_string(), row.root_ttr.to_string(),
+            row.hapax_legomena.to_string(), row.dis_legomena.to_string(), row.yules_k.to_string(), row.mtld.to_string(), hd_d,
+        ])?;
+    }
+
+    let duration = start.elapsed();
+    println!("\x1b[2m  Generating csv string in {} ms\x1b[0m", duration.as_millis());
+
+    finish(writer, output)
+}
+
+// Zipf and Heaps parameters, one row per file and one for the corpus
+pub fn zipf_to_csv_string(fits: &[ZipfFit], output: &OutputSettings) -> Result<String> {
+    // Example:
+    // Scope, Tokens, Types, Zipf Exponent (MLE), Zipf Exponent (Regression), Zipf R², Heaps K, Heaps β, Heaps R²
+    // book1, 52300, 6120, 1.05, 1.12, 0.97, 12.3, 0.52, 0.99
+
+    println!("\nGenerating a CSV string...");
+    let start = std::time::Instant::now();
+
+    let mut writer = csv_writer(output);
+    writer.write_record(["Scope", "Tokens", "Types", "Zipf Exponent (MLE)", "Zipf Exponent (Regression)", "Zipf R²", "Heaps K", "Heaps β", "Heaps R²"])?;
+    for fit in fits {
+        writer.write_record([
+            fit.scope.clone(), fit.tokens.to_string(), fit.types.to_string(),
+            float_field(fit.zipf_mle), float_field(fit.zipf_regression), float_field(fit.zipf_r2),
+            float_field(fit.heaps_k), float_field(fit.heaps_beta), float_field(fit.heaps_r2),
+        ])?;
+    }
+
+    let duration = start.elapsed();
+    println!("\x1b[2m  Generating csv string in {} ms\x1b[0m", duration.as_millis());
+
+    finish(writer, output)
+}
+
+// rank/frequency points of each file and of the corpus
+pub fn zipf_points_to_csv_string(fits: &[ZipfFit], output: &OutputSettings) -> Result<String> {
+    println!("\nGenerating a CSV string...");
+    let start = std::time::Instant::now();
+
+    let mut writer = csv_writer(output);
+    writer.write_record(["Scope", "Rank", "Words", "Frequency"])?;
+    for fit in fits {
+        for (i, (word, frequency)) in fit.ranks.iter().enumerate() {
+            writer.write_record([fit.scope.clone(), (i + 1).to_string(), word.clone(), frequency.to_string()])?;
+        }
+    }
+
+    let duration = start.elapsed();
+    println!("\x1b[2m  Generating csv string in {} ms\x1b[0m", duration.as_millis());
+
+    finish(writer, output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_terms_do_not_panic() {
+        let file_names = ["a".to_string()];
+        let words: HashSet<String> = ["".to_string(), "Ring".to_string()].into();
+        let data = HashMap::from([("a".to_string(), HashMap::from([("".to_string(), 1), ("Ring".to_string(), 2)]))]);
+        let table = combined_file_map_to_csv_string_usize(&file_names, &words, &data, &[], &OutputSettings::default()).unwrap();
+        assert_eq!(table, "Words,a,Total\nRing,2,2\n,1,1\n");
+    }
+
+    fn quoted_table(output: &OutputSettings) -> String {
+        let file_names = ["Smith, John.txt".to_string()];
+        let phrase = "say \"hi\", then".to_string();
+        let words: HashSet<String> = [phrase.clone(), "ring".to_string()].into();
+        let data = HashMap::from([(file_names[0].clone(), HashMap::from([(phrase.clone(), 2), ("ring".to_string(), 1)]))]);
+        combined_file_map_to_csv_string_usize(&file_names, &words, &data, &[phrase], output).unwrap()
+    }
+
+    #[test]
+    fn fields_are_quoted_and_escaped() {
+        let table = quoted_table(&OutputSettings::default());
+        assert_eq!(table, "Words,\"Smith, John.txt\",Total\n\"say \"\"hi\"\", then\",2,2\nring,1,1\n");
+    }
+
+    #[test]
+    fn delimiter_and_bom() {
+        let tab = quoted_table(&OutputSettings { delimiter: "\t".to_string(), ..OutputSettings::default() });
+        assert_eq!(tab, "Words\tSmith, John.txt\tTotal\n\"say \"\"hi\"\", then\"\t2\t2\nring\t1\t1\n");
+        let semicolon = quoted_table(&OutputSettings { delimiter: ";".to_string(), ..OutputSettings::default() });
+        assert!(semicolon.starts_with("Words;Smith, John.txt;Total\n"));
+
+        assert!(!semicolon.starts_with('\u{feff}'));
+        let bom = quoted_table(&OutputSettings { bom: true, ..OutputSettings::default() });
+        assert_eq!(bom.strip_prefix('\u{feff}'), Some(quoted_table(&OutputSettings::default()).as_str()));
+    }
+}
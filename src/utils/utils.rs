@@ -4,8 +4,15 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use rayon::prelude::*;
 use std::fs;
-use serde_json::Value;
 use lazy_static::lazy_static;
+use sha2::{Digest, Sha256};
+
+use crate::corpus::{Chapter, ChapterFrequencies, Corpus, Document};
+use crate::error::{Error, Result};
+use crate::normalizer::Normalizer;
+use crate::settings::Settings;
+use crate::tokenizer::Tokenizer;
+use crate::utils::{encoding, files};
 
 const STOPWORDS_STRING: &str = include_str!("stopwords.txt");
 fn parse_stopwords() -> HashSet<String> {
@@ -21,18 +28,41 @@ lazy_static! {
 }
 
 
-pub fn get_input(prompt: &str) -> String {
+/// SHA-256 of the built-in stopword list, in hex
+pub fn stopwords_hash() -> String {
+    sha256_hex(STOPWORDS_STRING.as_bytes())
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// exact match, as in the no-stopwords tables: the stopwords are lowercase, so a capitalized word such as "US" or "IT" is kept
+pub fn is_stopword(word: &str) -> bool {
+    STOPWORDS.contains(word)
+}
+
+// stopwords are lowercase, so the word is compared in lowercase (n-gram and collocation boundaries, where "The" starts many sentences)
+pub fn is_stopword_any_case(word: &str) -> bool {
+    STOPWORDS.contains(&word.to_lowercase())
+}
+
+// fails if the terminal cannot be read, or is closed (end of input), instead of asking again forever
+pub fn get_input(prompt: &str) -> Result<String> {
+    let stdin_error = |source: std::io::Error| Error::Io { path: PathBuf::from("<stdin>"), source };
     print!("{}", prompt);
-    stdout().flush().expect("Failed to flush stdout");
+    stdout().flush().map_err(|source| Error::Io { path: PathBuf::from("<stdout>"), source })?;
 
     let mut input = String::new();
-    stdin().read_line(&mut input).expect("Failed to read input");
+    if stdin().read_line(&mut input).map_err(stdin_error)? == 0 {
+        return Err(stdin_error(std::io::ErrorKind::UnexpectedEof.into()));
+    }
 
-    input.trim().to_string()
+    Ok(input.trim().to_string())
 }
 
-pub fn get_folder_dir(prompt: &str) -> String {
-    let mut input = get_input(prompt);
+pub fn get_folder_dir(prompt: &str) -> Result<String> {
+    let mut input = get_input(prompt)?;
     input = input.trim_matches('"').to_string();
     input = input.trim_matches('\"').to_string();
 
@@ -48,18 +78,18 @@ pub fn get_folder_dir(prompt: &str) -> String {
         return get_folder_dir(prompt);
     }
 
-    input
+    Ok(input)
 }
 
 // take in a prompt and an is_optinal arg, return the path String if it's valid, else return an empty String
-pub fn get_file_dir(prompt: &str, is_optional: bool) -> String {
-    let mut input = get_input(prompt);
+pub fn get_file_dir(prompt: &str, is_optional: bool) -> Result<String> {
+    let mut input = get_input(prompt)?;
     input = input.trim_matches('"').to_string();
     input = input.trim_matches('\"').to_string();
 
     // if input is empty and is_optional is true, return an empty String
     if input.is_empty() && is_optional {
-        return input;
+        return Ok(input);
     }
 
     // check if directory exists
@@ -74,15 +104,15 @@ pub fn get_file_dir(prompt: &str, is_optional: bool) -> String {
         return get_file_dir(prompt, is_optional);
     }
 
-    input
+    Ok(input)
 }
 
-pub fn get_json_path(prompt: &str) -> String {
-    let input = get_file_dir(prompt, true);
+pub fn get_json_path(prompt: &str) -> Result<String> {
+    let input = get_file_dir(prompt, true)?;
 
     // if input is empty, return an empty String
     if input.is_empty() {
-        return input;
+        return Ok(input);
     }
 
     // check if file is a .json file
@@ -91,44 +121,10 @@ pub fn get_json_path(prompt: &str) -> String {
         return get_json_path(prompt);
     }
 
-    input
-}
-
-pub fn get_phrases_from_json(json_path: &String) -> Vec<String> {
-    // return an empty Vec if json_path is empty
-    if json_path.is_empty() {
-        return Vec::new();
-    }
-
-    // read file
-    let phrases_txt = fs::read_to_string(&json_path).expect("Unable to read file");
-    let phrases_parsed: Value = serde_json::from_str(&phrases_txt).expect("JSON was not well-formatted");
-    // a vector of phrases to be targeted
-    let phrases: Vec<String> = phrases_parsed["phrases"].as_array().unwrap_or(&Vec::new()).iter().map(|s| s.as_str().unwrap().to_string()).collect();
-
-    phrases
+    Ok(input)
 }
 
-pub fn get_chapter_separator_from_json(json_path: &String, default: String) -> String {
-    // return default String if json_path is empty
-    if json_path.is_empty() {
-        return default;
-    }
-
-    // read file
-    let phrases_txt = fs::read_to_string(&json_path).expect("Unable to read file");
-    let phrases_parsed: Value = serde_json::from_str(&phrases_txt).expect("JSON was not well-formatted");
-    // a vector of phrases to be targeted
-    let mut chapter_separator = phrases_parsed["chapter_separator"].as_str().unwrap_or("<|eoc|>").to_string();
-    // if chapter_separator is empty, use default
-    if chapter_separator.is_empty() {
-        chapter_separator = default;
-    }
-
-    chapter_separator
-}
-
-fn split_into_chapters(text: &str, separator: &str) -> Vec<String> {
+pub(crate) fn split_into_chapters(text: &str, separator: &str) -> Vec<String> {
     let mut result: Vec<String> = Vec::new();
     for chapter in text.split(separator) {
         result.push(chapter.to_string());
@@ -137,64 +133,87 @@ fn split_into_chapters(text: &str, separator: &str) -> Vec<String> {
     result
 }
 
-fn trim_punctuations(text: &str) -> String {
-    // turn punctuations, except for "-" (and some other exceptions below) into whitespace, then replace multiple whitespaces with one
-    let mut result = String::new();
-    let text = text.to_string();
-    for c in text.chars() {
-        if c.is_alphanumeric() || c.is_whitespace() || c == '-' || c == '*' || c == '’' { // add some other exceptions here
-            result.push(c);
-        } else {
-            result.push(' ');
-        }
-    }
-    result = result.split_whitespace().collect::<Vec<&str>>().join(" ");
-    result
-}
+// Document names are the paths relative to the root folder, without the extension (e.g. "author/volume1")
+// If two files would end up with the same name (e.g. "a.txt" and "a.TXT"), both keep their extension
+pub(crate) fn document_names(root: &Path, file_list: &[PathBuf]) -> Vec<String> {
+    let without_extension: Vec<String> = file_list
+        .iter()
+        .map(|file| files::relative_path(root, &file.with_extension("")))
+        .collect();
 
-fn split_into_words(text: &str) -> Vec<String> {
-    let mut result: Vec<String> = Vec::new();
-    for word in text.split_whitespace() {
-        result.push(word.to_string());
+    let mut occurrences: HashMap<&String, usize> = HashMap::new();
+    for name in &without_extension {
+        *occurrences.entry(name).or_insert(0) += 1;
     }
 
-    result
+    file_list
+        .iter()
+        .zip(&without_extension)
+        .map(|(file, name)| {
+            if occurrences[name] > 1 {
+                files::relative_path(root, file)
+            } else {
+                name.clone()
+            }
+        })
+        .collect()
 }
 
-// HashMap of <file name, Vec<Vec<String>> of chapters<words>>
-fn file_collection(file_list: &Vec<PathBuf>, chapter_sep: &str) -> (HashMap<String, Vec<String>>, HashMap<String, Vec<Vec<String>>>) {
-    let mut result_chapters: HashMap<String, Vec<String>> = HashMap::new();
-    let mut result_words: HashMap<String, Vec<Vec<String>>> = HashMap::new();
-    for file in file_list {
-        let file_name = file.file_stem().unwrap().to_str().unwrap();
-        let text = fs::read_to_string(file).expect("Failed to read file");
-
-        let chapters = split_into_chapters(&text, chapter_sep);
-
-        // insert the raw text into the result_chapters hashmap
-        result_chapters.insert(file_name.to_string(), chapters.clone());
+// read a file and decode it to UTF-8, returns the text, the name of the encoding it was decoded from, and the raw bytes
+fn read_text(file: &Path, forced_encoding: Option<&'static encoding_rs::Encoding>) -> Result<(String, &'static str, Vec<u8>)> {
+    let bytes = fs::read(file).map_err(|source| Error::Io { path: file.to_path_buf(), source })?;
+    let (text, encoding) = encoding::decode(&bytes, forced_encoding).map_err(|reason| Error::Encoding { path: file.to_path_buf(), reason })?;
+    Ok((text, encoding, bytes))
+}
 
-        let mut chapters_words: Vec<Vec<String>> = Vec::new();
-        for chapter in chapters {
-            let words = split_into_words(&trim_punctuations(&chapter));
-            chapters_words.push(words);
-        }
-        result_words.insert(file_name.to_string(), chapters_words);
+// Read each file, split it into chapters, and split each chapter into words (capitalization is not normalized yet)
+// Files that cannot be read are skipped with a warning. The errors are returned along with the documents that were read
+fn file_collection(root: &Path, file_list: &[PathBuf], settings: &Settings, tokenizer: &dyn Tokenizer) -> Result<(Vec<Document>, Vec<Error>)> {
+    let forced_encoding = encoding::encoding_from_label(&settings.encoding)
+        .map_err(|reason| Error::Settings { path: PathBuf::new(), reason })?;
+
+    let mut result: Vec<Document> = Vec::new();
+    let mut errors: Vec<Error> = Vec::new();
+    for (file, file_name) in file_list.iter().zip(document_names(root, file_list)) {
+        let (text, encoding, bytes) = match read_text(file, forced_encoding) {
+            Ok(decoded) => decoded,
+            Err(error) => {
+                println!("\x1b[33m  WARNING: Skipping file. {}\x1b[0m", error);
+                errors.push(error);
+                continue;
+            }
+        };
+
+        // if there is no chapter separator, then the whole text is considered one chapter and the Vec is of length 1
+        let chapters = split_into_chapters(&text, &settings.chapter_separator)
+            .into_iter()
+            .map(|chapter| {
+                let words = tokenizer.tokenize(&chapter);
+                Chapter { text: chapter, tokens: words.len(), words, normalized: Vec::new() }
+            })
+            .collect();
+
+        result.push(Document {
+            name: file_name,
+            path: file.clone(),
+            encoding: encoding.to_string(),
+            size: bytes.len() as u64,
+            sha256: sha256_hex(&bytes),
+            chapters,
+        });
     }
 
-    // if there is no chapter separator, then the whole text is considered one chapter and the value is a Vec of length 1
-    // return the HashMap of raw data split by chapter, and the HashMap of raw data split by words
-    (result_chapters, result_words)
+    Ok((result, errors))
 }
 
-fn generate_word_corpus_set(file_collection: &HashMap<String, Vec<Vec<String>>>) -> HashSet<String> {
+fn generate_word_corpus_set(documents: &[Document]) -> HashSet<String> {
     println!("Generating word corpus...");
     // time start
     let start = std::time::Instant::now();
     let mut result: HashSet<String> = HashSet::new();
-    for (_, chapters) in file_collection {
-        for chapter in chapters {
-            for word in chapter {
+    for document in documents {
+        for chapter in &document.chapters {
+            for word in &chapter.words {
                 result.insert(word.to_string());
             }
         }
@@ -205,33 +224,65 @@ fn generate_word_corpus_set(file_collection: &HashMap<String, Vec<Vec<String>>>)
     result
 }
 
-// using the corpus, normalize the words in the file_collection and return a new file_collection
-pub fn digest_files(file_list: &Vec<PathBuf>, chapter_sep: &str) -> (HashMap<String, Vec<String>>, HashMap<String, Vec<Vec<String>>>, HashSet<String>, HashSet<String>) {
-    let file_collection = file_collection(file_list, chapter_sep);
-    let corpus = generate_word_corpus_set(&file_collection.1);
-    
+// using the corpus, normalize the words in the file_collection and return the digested Corpus
+// Fails only if none of the files could be read
+pub fn digest_files(root: &Path, file_list: &[PathBuf], settings: &Settings, tokenizer: &dyn Tokenizer) -> Result<Corpus> {
+    let (mut documents, mut errors) = file_collection(root, file_list, settings, tokenizer)?;
+    if documents.is_empty() {
+        return Err(match errors.is_empty() {
+            true => Error::NoFiles { path: root.to_path_buf(), include: settings.include.clone() },
+            false => errors.remove(0),
+        });
+    }
+    let corpus = generate_word_corpus_set(&documents);
+
     let start = std::time::Instant::now();
-    let mut result_words: HashMap<String, Vec<Vec<String>>> = HashMap::new();
     let mut result_words_corpus: HashSet<String> = HashSet::new(); // this is the corpus of the normalized words
-    for (file_name, chapters) in file_collection.1 {
-        let new_chapters = chapters.iter().map(|chapter| {
-            chapter.iter().map(|word| {
+    for document in &mut documents {
+        for chapter in &mut document.chapters {
+            for word in &mut chapter.words {
                 if let Some(lowercase_word) = corpus.get(&word.to_lowercase()) {
-                    result_words_corpus.insert(lowercase_word.to_string());
-                    lowercase_word.to_string()
-                } else {
-                    result_words_corpus.insert(word.to_string());
-                    word.to_string()
+                    *word = lowercase_word.to_string();
                 }
-            }).collect()
-        }).collect();
-        result_words.insert(file_name.to_string(), new_chapters);
+                result_words_corpus.insert(word.to_string());
+            }
+        }
     }
     let duration = start.elapsed();
     println!("\x1b[2m  Words' capitalization normalized in {} ms\x1b[0m", duration.as_millis());
 
-    // return both the HashMap and the corpus
-    (file_collection.0, result_words, corpus, result_words_corpus)
+    if let Some(normalizer) = Normalizer::from_settings(&settings.normalization)? {
+        normalize_words(&mut documents, &normalizer, &result_words_corpus);
+    }
+
+    Ok(Corpus { documents, vocabulary: corpus, normalized_vocabulary: result_words_corpus })
+}
+
+// stem or lemmatize every word, keeping the raw words alongside
+fn normalize_words(documents: &mut [Document], normalizer: &Normalizer, vocabulary: &HashSet<String>) {
+    let start = std::time::Instant::now();
+    // each unique word is normalized once
+    let normalized_forms: HashMap<&String, String> = vocabulary
+        .par_iter()
+        .map(|word| (word, normalizer.normalize(word, vocabulary)))
+        .collect();
+
+    for document in documents.iter_mut() {
+        for chapter in &mut document.chapters {
+            chapter.normalized = chapter.words.iter().map(|word| normalized_forms[word].clone()).collect();
+        }
+    }
+    let unique_terms: HashSet<&String> = normalized_forms.values().collect();
+    let duration = start.elapsed();
+    println!("\x1b[2m  Words normalized into {} unique terms in {} ms\x1b[0m", unique_terms.len(), duration.as_millis());
+}
+
+pub fn create_output_folder(path: &Path) -> Result<()> {
+    fs::create_dir_all(path).map_err(|source| Error::Output { path: path.to_path_buf(), source })
+}
+
+pub fn write_output_file(path: &Path, contents: &[u8]) -> Result<()> {
+    fs::write(path, contents).map_err(|source| Error::Output { path: path.to_path_buf(), source })
 }
 
 pub fn remove_stopwords_no_chapters(data: &HashMap<String, HashMap<String, usize>>) -> HashMap<String, HashMap<String, usize>> {
@@ -245,7 +296,7 @@ pub fn remove_stopwords_no_chapters(data: &HashMap<String, HashMap<String, usize
         .map(|(file_name, word_freq)| {
             let mut new_word_freq: HashMap<String, usize> = HashMap::new();
             for (word, freq) in word_freq {
-                if !STOPWORDS.contains(word) {
+                if !is_stopword(word) {
                     new_word_freq.insert(word.to_string(), *freq);
                 }
             }
@@ -258,20 +309,20 @@ pub fn remove_stopwords_no_chapters(data: &HashMap<String, HashMap<String, usize
     result
 }
 
-pub fn remove_stopwords_with_chapters(data: &HashMap<String, Vec<HashMap<String, usize>>>) -> HashMap<String, Vec<HashMap<String, usize>>> {
+pub fn remove_stopwords_with_chapters(data: &ChapterFrequencies<usize>) -> ChapterFrequencies<usize> {
     // data is <file name, chapters<word, frequency>>
     // delete stopwords from each file
     let start = std::time::Instant::now();
 
     // use parallel iterator
-    let result: HashMap<String, Vec<HashMap<String, usize>>> = data
+    let result: ChapterFrequencies<usize> = data
         .par_iter()
         .map(|(file_name, chapters)| {
             let mut new_chapters: Vec<HashMap<String, usize>> = Vec::new();
             for chapter in chapters {
                 let mut new_word_freq: HashMap<String, usize> = HashMap::new();
                 for (word, freq) in chapter {
-                    if !STOPWORDS.contains(word) {
+                    if !is_stopword(word) {
                         new_word_freq.insert(word.to_string(), *freq);
                     }
                 }
@@ -284,4 +335,23 @@ pub fn remove_stopwords_with_chapters(data: &HashMap<String, Vec<HashMap<String,
     let duration = start.elapsed();
     println!("\x1b[2m  Stopwords removed in {} ms\x1b[0m", duration.as_millis());
     result
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stopwords_capitalization() {
+        assert!(is_stopword("the") && !is_stopword("The") && !is_stopword("IT"));
+        assert!(is_stopword_any_case("the") && is_stopword_any_case("The") && is_stopword_any_case("THE"));
+        assert!(!is_stopword_any_case("dragon"));
+
+        // the tables only drop exact matches
+        let chapter: HashMap<String, usize> = [("The", 2), ("the", 3), ("Dragon", 1)].iter().map(|(word, count)| (word.to_string(), *count)).collect();
+        let data: ChapterFrequencies<usize> = HashMap::from([("book".to_string(), vec![chapter.clone()])]);
+        let expected: HashMap<String, usize> = HashMap::from([("The".to_string(), 2), ("Dragon".to_string(), 1)]);
+        assert_eq!(remove_stopwords_with_chapters(&data)["book"], vec![expected.clone()]);
+        assert_eq!(remove_stopwords_no_chapters(&HashMap::from([("book".to_string(), chapter)]))["book"], expected);
+    }
+}
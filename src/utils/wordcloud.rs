@@ -0,0 +1,224 @@
+// Word cloud layout (spiral placement with collision detection) and rendering to SVG and PNG
+
+use std::fmt::Write;
+use std::path::Path;
+use std::fs;
+
+use fontdue::{Font, FontSettings};
+
+use crate::error::{Error, Result};
+use crate::utils::graph;
+
+// font used when no font file is set, so that the PNG word clouds can be drawn on any machine (license: assets/fonts/LICENSE-DejaVu.txt)
+const DEFAULT_FONT: &[u8] = include_bytes!("../../assets/fonts/DejaVuSans-Bold.ttf");
+
+// width of a character, in em, when there is no font to measure it
+const FALLBACK_CHAR_WIDTH: f32 = 0.62;
+
+/// A word placed on the cloud: (x, y) is the top-left corner of its box
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedWord {
+    pub word: String,
+    pub font_size: f32,
+    pub color: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Distance from the top of the box to the baseline
+    pub ascent: f32,
+}
+
+/// Parse a "#rrggbb" color
+pub fn parse_color(color: &str) -> std::result::Result<(u8, u8, u8), String> {
+    let hex = color.strip_prefix('#').filter(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()));
+    match hex {
+        Some(hex) => {
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+            Ok((channel(0), channel(2), channel(4)))
+        }
+        None => Err(format!("invalid color \"{}\" (expected #rrggbb)", color)),
+    }
+}
+
+/// Load the font file, or the embedded DejaVu Sans Bold if `font_file` is empty
+pub fn load_font(font_file: &str) -> Result<Font> {
+    if font_file.is_empty() {
+        return Ok(Font::from_bytes(DEFAULT_FONT, FontSettings::default()).expect("the embedded font is valid"));
+    }
+    let path = Path::new(font_file);
+    let bytes = fs::read(path).map_err(|source| Error::Io { path: path.to_path_buf(), source })?;
+    Font::from_bytes(bytes, FontSettings::default())
+        .map_err(|reason| Error::Settings { path: path.to_path_buf(), reason: format!("not a usable font: {}", reason) })
+}
+
+// (width, height, ascent) of a word's box
+fn measure(word: &str, font_size: f32, font: Option<&Font>) -> (f32, f32, f32) {
+    match font {
+        Some(font) => {
+            let width: f32 = word.chars().map(|c| font.metrics(c, font_size).advance_width).sum();
+            match font.horizontal_line_metrics(font_size) {
+                Some(line) => (width, line.ascent - line.descent, line.ascent),
+                None => (width, font_size * 1.2, font_size),
+            }
+        }
+        None => (word.chars().count() as f32 * font_size * FALLBACK_CHAR_WIDTH, font_size * 1.2, font_size),
+    }
+}
+
+fn overlaps(a: &PlacedWord, x: f32, y: f32, width: f32, height: f32) -> bool {
+    x < a.x + a.width && a.x < x + width && y < a.y + a.height && a.y < y + height
+}
+
+/// Place the words, largest first, along an Archimedean spiral from the center of the canvas.
+/// A word that does not fit anywhere is retried smaller, then left out once below `min_font_size`
+pub fn layout(words: &[(String, f32)], width: f32, height: f32, min_font_size: f32, palette: &[String], font: Option<&Font>) -> Vec<PlacedWord> {
+    let mut placed: Vec<PlacedWord> = Vec::new();
+    let (center_x, center_y) = (width / 2.0, height / 2.0);
+    // the spiral is stretched to the shape of the canvas
+    let aspect = height / width;
+    let max_radius = (width * width + height * height).sqrt() / 2.0;
+    let margin = 2.0;
+
+    for (i, (word, size)) in words.iter().enumerate() {
+        let mut font_size = *size;
+        while font_size >= min_font_size {
+            let (word_width, word_height, ascent) = measure(word, font_size, font);
+            let (box_width, box_height) = (word_width + margin, word_height + margin);
+            let mut position: Option<(f32, f32)> = None;
+            if box_width <= width && box_height <= height {
+                let mut t: f32 = 0.0;
+                loop {
+                    let radius = 2.0 * t;
+                    if radius > max_radius {
+                        break;
+                    }
+                    let x = center_x + radius * t.cos() - box_width / 2.0;
+                    let y = center_y + radius * aspect * t.sin() - box_height / 2.0;
+                    let inside = x >= 0.0 && y >= 0.0 && x + box_width <= width && y + box_height <= height;
+                    if inside && !placed.iter().any(|other| overlaps(other, x, y, box_width, box_height)) {
+                        position = Some((x, y));
+                        break;
+                    }
+                    // roughly constant steps along the spiral
+                    t += (1.0 / (1.0 + radius)).max(0.02);
+                }
+            }
+
+            if let Some((x, y)) = position {
+                placed.push(PlacedWord {
+                    word: word.clone(),
+                    font_size,
+                    color: palette[i % palette.len()].clone(),
+                    x,
+                    y,
+                    width: box_width,
+                    height: box_height,
+                    ascent,
+                });
+                break;
+            }
+            font_size *= 0.9;
+        }
+    }
+
+    placed
+}
+
+pub fn to_svg(placed: &[PlacedWord], width: f32, height: f32, background: &str) -> String {
+    let mut svg = String::new();
+    let _ = writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">", w = width, h = height);
+    let _ = writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>", background);
+    for word in placed {
+        // textLength keeps the word inside its box whatever font the viewer uses
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"{:.1}\" font-family=\"DejaVu Sans, Arial, Helvetica, sans-serif\" font-weight=\"bold\" fill=\"{}\" textLength=\"{:.1}\" lengthAdjust=\"spacingAndGlyphs\">{}</text>",
+            word.x + 1.0, word.y + 1.0 + word.ascent, word.font_size, word.color, word.width - 2.0, graph::escape(&word.word)
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Rasterize the cloud with the font and encode it as a PNG image
+pub fn to_png(placed: &[PlacedWord], width: u32, height: u32, background: &str, font: &Font) -> std::result::Result<Vec<u8>, png::EncodingError> {
+    let background = parse_color(background).unwrap_or((255, 255, 255));
+    // 3 bytes per pixel, the size is checked in the settings but the library can be called with any size
+    let (width_px, height_px) = (width as usize, height as usize);
+    let count = width_px.checked_mul(height_px).filter(|count| count.checked_mul(3).is_some()).ok_or(png::EncodingError::LimitsExceeded)?;
+    let mut pixels: Vec<u8> = [background.0, background.1, background.2].repeat(count);
+
+    for word in placed {
+        let color = parse_color(&word.color).unwrap_or((0, 0, 0));
+        let baseline = word.y + 1.0 + word.ascent;
+        let mut pen_x = word.x + 1.0;
+        for c in word.word.chars() {
+            let (metrics, coverage) = font.rasterize(c, word.font_size);
+            let left = (pen_x + metrics.xmin as f32).round() as i64;
+            let top = (baseline - metrics.height as f32 - metrics.ymin as f32).round() as i64;
+            for row in 0..metrics.height {
+                for column in 0..metrics.width {
+                    let (x, y) = (left + column as i64, top + row as i64);
+                    if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+                        continue;
+                    }
+                    let alpha = coverage[row * metrics.width + column] as f32 / 255.0;
+                    let index = (y as usize * width_px + x as usize) * 3;
+                    for (offset, channel) in [color.0, color.1, color.2].into_iter().enumerate() {
+                        let old = pixels[index + offset] as f32;
+                        pixels[index + offset] = (old + (channel as f32 - old) * alpha).round() as u8;
+                    }
+                }
+            }
+            pen_x += metrics.advance_width;
+        }
+    }
+
+    let mut result: Vec<u8> = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut result, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&pixels)?;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_layout(placed: &[PlacedWord], width: f32, height: f32) {
+        for (i, word) in placed.iter().enumerate() {
+            assert!(word.x >= 0.0 && word.y >= 0.0 && word.x + word.width <= width && word.y + word.height <= height, "{:?} is outside", word);
+            for other in &placed[i + 1..] {
+                assert!(!overlaps(other, word.x, word.y, word.width, word.height), "{:?} overlaps {:?}", word, other);
+            }
+        }
+    }
+
+    #[test]
+    fn layout_without_overlaps() {
+        let words: Vec<(String, f32)> = (0..40).map(|i| (format!("word{}", i), 60.0 - i as f32)).collect();
+        let palette = ["#000000".to_string()];
+        let font = load_font("").unwrap();
+        for font in [None, Some(&font)] {
+            let placed = layout(&words, 400.0, 250.0, 10.0, &palette, font);
+            assert!(placed.len() > 10);
+            assert_eq!(placed[0].word, "word0");
+            assert_layout(&placed, 400.0, 250.0);
+        }
+        // a word wider than the canvas at every size is left out
+        assert!(layout(&[("w".repeat(50), 40.0)], 100.0, 100.0, 30.0, &palette, None).is_empty());
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#1f4E79"), Ok((0x1f, 0x4e, 0x79)));
+        assert!(parse_color("#abc").is_err());
+        assert!(parse_color("red").is_err());
+        assert!(parse_color("#12345g").is_err());
+    }
+}